@@ -0,0 +1,383 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use log::{debug, trace};
+use thiserror::Error;
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+#[derive(Debug, Error)]
+pub enum ExtractError {
+    #[error("unsupported archive: {0}")]
+    Unsupported(String),
+
+    #[error("{path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("invalid zip archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("unsafe archive entry {entry}: {reason}")]
+    UnsafeEntry { entry: String, reason: &'static str },
+}
+
+trait IoContext<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, ExtractError>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> Result<T, ExtractError> {
+        self.map_err(|source| ExtractError::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// 支持解压的压缩格式。顺序即下载时的优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+    TarXz,
+    TarBz2,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 4] = [
+        ArchiveFormat::TarGz,
+        ArchiveFormat::Zip,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::TarBz2,
+    ];
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::TarXz => ".tar.xz",
+            ArchiveFormat::TarBz2 => ".tar.bz2",
+        }
+    }
+
+    /// 从文件名后缀识别压缩格式
+    pub fn from_filename(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| name.ends_with(f.suffix()))
+    }
+}
+
+/// 解压文件`from`到目录`to`中
+pub fn extract<P: AsRef<Path>>(from: P, to: P) -> Result<(), ExtractError> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    let format = from
+        .file_name()
+        .and_then(|s| s.to_str())
+        .and_then(ArchiveFormat::from_filename)
+        .ok_or_else(|| ExtractError::Unsupported(from.display().to_string()))?;
+    debug!(
+        "extracting {:?} {} to {}",
+        format,
+        from.display(),
+        to.display()
+    );
+
+    let file = BufReader::new(File::open(from).with_path(from)?);
+    fs::create_dir_all(to).with_path(to)?;
+    match format {
+        ArchiveFormat::TarGz => extract_tar(GzDecoder::new(file), from, to),
+        ArchiveFormat::TarXz => extract_tar(XzDecoder::new(file), from, to),
+        ArchiveFormat::TarBz2 => extract_tar(BzDecoder::new(file), from, to),
+        ArchiveFormat::Zip => extract_zip(file, to),
+    }
+}
+
+/// 校验压缩文件中的条目，防止解压时写入到目标目录之外
+#[derive(Debug, Default)]
+struct EntryValidator {
+    /// 已解压的符号链接条目
+    symlinks: Vec<PathBuf>,
+}
+
+impl EntryValidator {
+    /// 条目路径必须是相对路径，不能包含`..`，也不能经过已解压的符号链接
+    fn check_path(&self, path: &Path) -> Result<(), ExtractError> {
+        let unsafe_entry = |reason| ExtractError::UnsafeEntry {
+            entry: path.display().to_string(),
+            reason,
+        };
+        for comp in path.components() {
+            match comp {
+                Component::Prefix(_) | Component::RootDir => {
+                    return Err(unsafe_entry("absolute path"))
+                }
+                Component::ParentDir => return Err(unsafe_entry("contains `..`")),
+                Component::CurDir | Component::Normal(_) => {}
+            }
+        }
+        if path
+            .ancestors()
+            .skip(1)
+            .any(|p| self.symlinks.iter().any(|link| link == p))
+        {
+            return Err(unsafe_entry("path through a symlink"));
+        }
+        Ok(())
+    }
+
+    /// 符号链接`path -> target`的目标必须指向其所在的顶层目录即maven home内。
+    /// `..`只允许出现在`target`开头
+    fn check_symlink(&mut self, path: &Path, target: &Path) -> Result<(), ExtractError> {
+        self.check_path(path)?;
+        let unsafe_entry = |reason| ExtractError::UnsafeEntry {
+            entry: format!("{} -> {}", path.display(), target.display()),
+            reason,
+        };
+        // 相对于顶层目录的深度
+        let mut depth = path
+            .parent()
+            .map(|p| {
+                p.components()
+                    .filter(|c| matches!(c, Component::Normal(_)))
+                    .count()
+            })
+            .unwrap_or(0)
+            .saturating_sub(1);
+        let mut descended = false;
+        for comp in target.components() {
+            match comp {
+                Component::Prefix(_) | Component::RootDir => {
+                    return Err(unsafe_entry("absolute symlink target"))
+                }
+                Component::ParentDir if descended => {
+                    return Err(unsafe_entry("`..` in the middle of symlink target"))
+                }
+                Component::ParentDir if depth == 0 => {
+                    return Err(unsafe_entry("symlink target outside of maven home"))
+                }
+                Component::ParentDir => depth -= 1,
+                Component::Normal(_) => {
+                    descended = true;
+                    depth += 1;
+                }
+                Component::CurDir => {}
+            }
+        }
+        self.symlinks.push(normalize(path));
+        Ok(())
+    }
+}
+
+/// 移除路径中的`.`
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+fn extract_tar(reader: impl Read, from: &Path, to: &Path) -> Result<(), ExtractError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut validator = EntryValidator::default();
+    for entry in archive.entries().with_path(from)? {
+        let mut entry = entry.with_path(from)?;
+        let path = normalize(&entry.path().with_path(from)?);
+        let link = entry.link_name().with_path(from)?.map(|p| p.into_owned());
+        match (entry.header().entry_type(), link) {
+            (tar::EntryType::Symlink, Some(target)) => validator.check_symlink(&path, &target)?,
+            // 硬链接的目标是相对于压缩文件根目录的路径
+            (tar::EntryType::Link, Some(target)) => {
+                validator.check_path(&path)?;
+                validator.check_path(&target)?;
+            }
+            _ => validator.check_path(&path)?,
+        }
+        trace!("unpacking {}", path.display());
+        entry.unpack_in(to).with_path(to.join(&path))?;
+    }
+    Ok(())
+}
+
+fn extract_zip(reader: impl Read + io::Seek, to: &Path) -> Result<(), ExtractError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut validator = EntryValidator::default();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let entry = normalize(Path::new(file.name()));
+        let path = to.join(&entry);
+        trace!("unpacking {}", path.display());
+        if is_zip_symlink(file.unix_mode()) {
+            let mut target = String::new();
+            file.read_to_string(&mut target).with_path(&path)?;
+            validator.check_symlink(&entry, Path::new(&target))?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&target, &path).with_path(&path)?;
+            continue;
+        }
+        validator.check_path(&entry)?;
+        if file.is_dir() {
+            fs::create_dir_all(&path).with_path(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        let mut out = File::create(&path).with_path(&path)?;
+        io::copy(&mut file, &mut out).with_path(&path)?;
+        #[cfg(unix)]
+        if let Some(mode) = file.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).with_path(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_zip_symlink(mode: Option<u32>) -> bool {
+    const S_IFMT: u32 = 0o170000;
+    const S_IFLNK: u32 = 0o120000;
+    mode.map(|m| m & S_IFMT == S_IFLNK).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use tempfile::tempdir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    static MVN: &str = "#!/bin/sh\necho mvn\n";
+
+    fn tar_builder<W: Write>(w: W) -> io::Result<tar::Builder<W>> {
+        let mut builder = tar::Builder::new(w);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(MVN.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, "apache-maven-3.8.4/bin/mvn", MVN.as_bytes())?;
+        Ok(builder)
+    }
+
+    fn assert_mvn(to: &Path) {
+        let mvn = to.join("apache-maven-3.8.4/bin/mvn");
+        assert_eq!(fs::read_to_string(&mvn).unwrap(), MVN);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(mvn.metadata().unwrap().permissions().mode() & 0o111, 0o111);
+        }
+    }
+
+    #[test]
+    fn test_archive_format() {
+        assert_eq!(
+            ArchiveFormat::from_filename("apache-maven-3.8.4-bin.tar.gz"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_filename("apache-maven-3.8.4-bin.zip"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_filename("apache-maven-3.8.4-bin.tar.gz.asc"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_tar_gz() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("apache-maven-3.8.4-bin.tar.gz");
+        tar_builder(GzEncoder::new(File::create(&from)?, Compression::default()))?
+            .into_inner()?
+            .finish()?;
+
+        let to = dir.path().join("out");
+        extract(&from, &to)?;
+        assert_mvn(&to);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_zip() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("apache-maven-3.8.4-bin.zip");
+        let mut zip = ZipWriter::new(File::create(&from)?);
+        zip.add_directory("apache-maven-3.8.4/bin/", FileOptions::default())?;
+        zip.start_file(
+            "apache-maven-3.8.4/bin/mvn",
+            FileOptions::default().unix_permissions(0o755),
+        )?;
+        zip.write_all(MVN.as_bytes())?;
+        zip.finish()?;
+
+        let to = dir.path().join("out");
+        extract(&from, &to)?;
+        assert_mvn(&to);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_path() {
+        let v = EntryValidator::default();
+        assert!(v
+            .check_path(Path::new("apache-maven-3.8.4/bin/mvn"))
+            .is_ok());
+        assert!(v.check_path(Path::new("/etc/passwd")).is_err());
+        assert!(v.check_path(Path::new("apache-maven/../../evil")).is_err());
+    }
+
+    #[test]
+    fn test_check_symlink() {
+        let mut v = EntryValidator::default();
+        let check = |v: &mut EntryValidator, path: &str, target: &str| {
+            v.check_symlink(Path::new(path), Path::new(target))
+        };
+        assert!(check(&mut v, "apache-maven/lib/a.jar", "../boot/a.jar").is_ok());
+        assert!(check(&mut v, "apache-maven/lib/b.jar", "/etc/passwd").is_err());
+        assert!(check(&mut v, "apache-maven/lib/c.jar", "../../../c.jar").is_err());
+        assert!(check(&mut v, "apache-maven/lib/d.jar", "../boot/../../../d.jar").is_err());
+        // 指向maven home之外，即使仍在解压目录内
+        assert!(check(&mut v, "apache-maven/lib/e.jar", "../../other/e.jar").is_err());
+        assert!(check(&mut v, "apache-maven/up", "..").is_err());
+        assert!(check(&mut v, "apache-maven/lib/home", "..").is_ok());
+
+        // 经过已解压的符号链接写入
+        assert!(check(&mut v, "apache-maven/libs", "lib").is_ok());
+        assert!(v.check_path(Path::new("apache-maven/libs/evil")).is_err());
+    }
+
+    #[test]
+    fn test_extract_tar_symlink_escape() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("apache-maven-3.8.4-bin.tar.gz");
+        let mut builder =
+            tar_builder(GzEncoder::new(File::create(&from)?, Compression::default()))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "apache-maven-3.8.4/conf", "/etc")?;
+        builder.into_inner()?.finish()?;
+
+        let to = dir.path().join("out");
+        match extract(&from, &to) {
+            Err(ExtractError::UnsafeEntry { entry, .. }) => {
+                assert_eq!(entry, "apache-maven-3.8.4/conf -> /etc")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_unsupported() {
+        let res = extract(Path::new("apache-maven-3.8.4-bin.rar"), Path::new("out"));
+        assert!(matches!(res, Err(ExtractError::Unsupported(_))));
+    }
+}
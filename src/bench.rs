@@ -0,0 +1,161 @@
+use std::{cmp::Ordering, time::Duration};
+
+use anyhow::{anyhow, Error};
+use getset::Getters;
+use log::{debug, warn};
+use tokio::time::Instant;
+use url::Url;
+
+use crate::{site::Site, source::Source};
+
+/// 测量延迟使用的版本索引目录
+static INDEX_LINE: &str = "maven-3";
+
+/// 镜像的测速结果
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct BenchResult {
+    mirror: Url,
+    /// 获取`maven/maven-3/`索引的耗时
+    latency: Option<Duration>,
+    /// 下载最新版本二进制文件的速度，单位：字节/秒
+    throughput: Option<f64>,
+    error: Option<String>,
+}
+
+impl BenchResult {
+    fn failed(mirror: Url, latency: Option<Duration>, e: Error) -> Self {
+        warn!("failed to bench mirror {}: {}", mirror, e);
+        Self {
+            mirror,
+            latency,
+            throughput: None,
+            error: Some(e.to_string()),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 测量镜像`site`获取`maven/maven-3/`索引的延迟，
+/// 及下载该索引中最新正式版本二进制文件开头`len`个字节的速度
+pub async fn bench(site: &Site, len: u64) -> BenchResult {
+    let mirror = site.mirror().clone();
+    let start = Instant::now();
+    let versions = match site.fetch_line_versions(INDEX_LINE).await {
+        Ok(versions) => versions,
+        Err(e) => return BenchResult::failed(mirror, None, e),
+    };
+    let latency = start.elapsed();
+    debug!("fetched index of {} in {:?}", mirror, latency);
+
+    let res = async {
+        let latest = versions
+            .iter()
+            .filter(|v| !v.is_prerelease())
+            .max()
+            .ok_or_else(|| anyhow!("not found any version"))?;
+        let bins = site.fetch_bins(latest).await?;
+        let bin = bins
+            .first()
+            .ok_or_else(|| anyhow!("not found any bin of {}", latest))?;
+        let start = Instant::now();
+        let read = bin.read_head(len).await?;
+        let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+        debug!("read {} bytes of {} in {}s", read, bin.url(), elapsed);
+        Ok::<_, Error>(read as f64 / elapsed)
+    }
+    .await;
+    match res {
+        Ok(throughput) => BenchResult {
+            mirror,
+            latency: Some(latency),
+            throughput: Some(throughput),
+            error: None,
+        },
+        Err(e) => BenchResult::failed(mirror, Some(latency), e),
+    }
+}
+
+/// 按下载速度降序、延迟升序排序，失败的镜像在最后
+pub fn rank(results: &mut [BenchResult]) {
+    results.sort_by(|a, b| {
+        b.is_ok()
+            .cmp(&a.is_ok())
+            .then_with(|| {
+                let (a, b) = (a.throughput.unwrap_or(0.0), b.throughput.unwrap_or(0.0));
+                b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+            })
+            .then_with(|| {
+                a.latency
+                    .unwrap_or(Duration::MAX)
+                    .cmp(&b.latency.unwrap_or(Duration::MAX))
+            })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn result(mirror: &str, latency: Option<u64>, throughput: Option<f64>) -> BenchResult {
+        BenchResult {
+            mirror: mirror.parse().unwrap(),
+            latency: latency.map(Duration::from_millis),
+            throughput,
+            error: throughput.is_none().then(|| "failed".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut results = vec![
+            result("https://a.example.com/", None, None),
+            result("https://b.example.com/", Some(100), Some(1000.0)),
+            result("https://c.example.com/", Some(50), Some(1000.0)),
+            result("https://d.example.com/", Some(10), Some(10.0)),
+        ];
+        rank(&mut results);
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| r.mirror.host_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "c.example.com",
+                "b.example.com",
+                "d.example.com",
+                "a.example.com"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bench_local() -> Result<()> {
+        let dir = tempdir()?;
+        for ver in ["3.8.4", "3.9.0-alpha-1"] {
+            let bin_dir = dir.path().join(format!("maven/maven-3/{}/binaries", ver));
+            fs::create_dir_all(&bin_dir)?;
+            fs::write(
+                bin_dir.join(format!("apache-maven-{}-bin.zip", ver)),
+                vec![0; 4096],
+            )?;
+        }
+        let site = Site::new(Url::from_directory_path(dir.path()).unwrap())?;
+        let res = bench(&site, 1024).await;
+        assert!(res.is_ok(), "{:?}", res.error());
+        assert!(res.latency().is_some());
+        assert!(res.throughput().unwrap() > 0.0);
+
+        let site = Site::new(Url::from_directory_path(dir.path().join("missing")).unwrap())?;
+        let res = bench(&site, 1024).await;
+        assert!(!res.is_ok());
+        assert_eq!(res.latency(), &None);
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::join;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::{
+    site::{fetch_cxt, fetch_digest, BinFile, Digest, Site},
+    source::Source,
+    version::Version,
+};
+
+/// apache镜像选择器，返回离当前位置最近的下载镜像
+pub static CLOSER_URL: &str = "https://www.apache.org/dyn/closer.lua?as_json=1";
+
+/// 保存所有历史版本的apache归档站点
+pub static ARCHIVE_URL: &str = "https://archive.apache.org/dist/";
+
+/// `closer.lua?as_json=1`的响应，如：
+///
+/// ```json
+/// {"backup": ["https://downloads.apache.org/"], "preferred": "https://dlcdn.apache.org/", ...}
+/// ```
+#[derive(Debug, Deserialize)]
+struct CloserResponse {
+    preferred: Url,
+    #[serde(default)]
+    http: Vec<Url>,
+    #[serde(default)]
+    backup: Vec<Url>,
+}
+
+impl CloserResponse {
+    /// 所有候选镜像，首选镜像在前
+    fn mirrors(self) -> Vec<Url> {
+        let mut mirrors: Vec<Url> = vec![];
+        for url in std::iter::once(self.preferred)
+            .chain(self.http)
+            .chain(self.backup)
+            .map(dir_url)
+        {
+            if !mirrors.contains(&url) {
+                mirrors.push(url);
+            }
+        }
+        mirrors
+    }
+}
+
+/// 自动选择的镜像。当前发布的版本从选择器解析出的首选镜像下载，
+/// 已从首选镜像移除的旧版本回退到归档站点
+#[derive(Debug)]
+pub struct AutoSite {
+    selector: Url,
+    archive: Site,
+    preferred: OnceCell<Option<Site>>,
+}
+
+impl AutoSite {
+    pub fn new() -> Result<Self> {
+        Self::with_selector(CLOSER_URL.parse()?, Site::new(ARCHIVE_URL)?)
+    }
+
+    pub fn with_selector(selector: Url, archive: Site) -> Result<Self> {
+        Ok(Self {
+            selector,
+            archive,
+            preferred: OnceCell::new(),
+        })
+    }
+
+    /// 已解析出的首选镜像。未解析或解析失败时为`None`
+    pub fn resolved(&self) -> Option<&Url> {
+        self.preferred
+            .get()
+            .and_then(Option::as_ref)
+            .map(Site::mirror)
+    }
+
+    /// 从选择器解析首选镜像。解析失败时只使用归档站点
+    async fn preferred(&self) -> Option<&Site> {
+        self.preferred
+            .get_or_init(|| async {
+                match fetch_preferred(&self.selector).await.and_then(Site::new) {
+                    Ok(site) => {
+                        info!("resolved preferred mirror {}", site.mirror());
+                        Some(site)
+                    }
+                    Err(e) => {
+                        warn!(
+                            "failed to resolve mirror from {}, using {}: {}",
+                            self.selector,
+                            self.archive.mirror(),
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()
+    }
+}
+
+#[async_trait]
+impl Source for AutoSite {
+    /// 解析出的首选镜像，未解析时为归档站点
+    fn url(&self) -> &Url {
+        self.resolved().unwrap_or_else(|| self.archive.mirror())
+    }
+
+    /// 合并首选镜像与归档站点的版本
+    async fn fetch_versions(&self) -> Result<Vec<Version>> {
+        let preferred = match self.preferred().await {
+            Some(site) => site,
+            None => return self.archive.fetch_versions().await,
+        };
+        let (current, archived) = join!(preferred.fetch_versions(), self.archive.fetch_versions());
+        let mut versions = match (current, archived) {
+            (Ok(mut current), Ok(archived)) => {
+                current.extend(archived);
+                current
+            }
+            (Ok(vers), Err(e)) | (Err(e), Ok(vers)) => {
+                warn!("failed to fetch versions from one of the mirrors: {}", e);
+                vers
+            }
+            (Err(e), Err(_)) => return Err(e),
+        };
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        versions.dedup();
+        Ok(versions)
+    }
+
+    async fn fetch_bins(&self, ver: &Version) -> Result<Vec<BinFile>> {
+        if let Some(site) = self.preferred().await {
+            match site.fetch_bins(ver).await {
+                Ok(bins) => return Ok(bins),
+                Err(e) => debug!(
+                    "not found {} in {}, falling back to {}: {}",
+                    ver,
+                    site.mirror(),
+                    self.archive.mirror(),
+                    e
+                ),
+            }
+        }
+        self.archive.fetch_bins(ver).await
+    }
+
+    async fn fetch_digest(&self, bin_url: &Url) -> Result<Option<Digest>> {
+        fetch_digest(bin_url).await
+    }
+
+    async fn fetch_keys(&self) -> Result<String> {
+        if let Some(site) = self.preferred().await {
+            match site.fetch_keys().await {
+                Ok(keys) => return Ok(keys),
+                Err(e) => debug!("failed to fetch KEYS from {}: {}", site.mirror(), e),
+            }
+        }
+        self.archive.fetch_keys().await
+    }
+}
+
+/// 请求选择器`selector`获取首选镜像
+async fn fetch_preferred(selector: &Url) -> Result<Url> {
+    debug!("resolving preferred mirror from {}", selector);
+    let content = fetch_cxt(selector.clone()).await?;
+    parse_closer(&content).map(|resp| dir_url(resp.preferred))
+}
+
+/// 从apache镜像选择器发现所有候选镜像，首选镜像在前
+pub async fn discover_mirrors() -> Result<Vec<Url>> {
+    debug!("discovering mirrors from {}", CLOSER_URL);
+    let content = fetch_cxt(CLOSER_URL.parse()?).await?;
+    parse_closer(&content).map(CloserResponse::mirrors)
+}
+
+fn parse_closer(content: &str) -> Result<CloserResponse> {
+    serde_json::from_str::<CloserResponse>(content)
+        .map_err(|e| anyhow!("invalid mirror selector response: {}", e))
+}
+
+/// 镜像目录地址总是以`/`结尾
+fn dir_url(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_closer() -> Result<()> {
+        let content = r#"{"backup": ["https://downloads.apache.org/"], "cca2": "us", "ftp": [], "http": ["https://dlcdn.apache.org/"], "path_info": "", "preferred": "https://dlcdn.apache.org"}"#;
+        let resp = parse_closer(content)?;
+        assert_eq!(resp.preferred.as_str(), "https://dlcdn.apache.org/");
+        assert_eq!(
+            resp.mirrors().iter().map(Url::as_str).collect::<Vec<_>>(),
+            vec!["https://dlcdn.apache.org/", "https://downloads.apache.org/"]
+        );
+        assert!(parse_closer("<html></html>").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fallback_to_archive() -> Result<()> {
+        let dir = tempdir()?;
+        let (dist, archive) = (dir.path().join("dist"), dir.path().join("archive"));
+        for (root, ver) in [(&dist, "3.9.6"), (&archive, "3.9.6"), (&archive, "3.6.3")] {
+            let bin_dir = root.join(format!("maven/maven-3/{}/binaries", ver));
+            fs::create_dir_all(&bin_dir)?;
+            fs::write(bin_dir.join(format!("apache-maven-{}-bin.zip", ver)), "zip")?;
+        }
+        let dist_url = Url::from_directory_path(&dist).unwrap();
+        let selector = dir.path().join("closer.json");
+        fs::write(&selector, format!(r#"{{"preferred": "{}"}}"#, dist_url))?;
+
+        let site = AutoSite::with_selector(
+            Url::from_file_path(&selector).unwrap(),
+            Site::new(Url::from_directory_path(&archive).unwrap())?,
+        )?;
+        assert_eq!(site.resolved(), None);
+        assert_eq!(
+            site.fetch_versions().await?,
+            vec!["3.9.6".parse()?, "3.6.3".parse()?]
+        );
+        assert_eq!(site.resolved(), Some(&dist_url));
+        assert_eq!(site.url(), &dist_url);
+
+        let bins = site.fetch_bins(&"3.9.6".parse()?).await?;
+        assert!(bins[0].url().as_str().starts_with(dist_url.as_str()));
+        let bins = site.fetch_bins(&"3.6.3".parse()?).await?;
+        assert!(!bins[0].url().as_str().starts_with(dist_url.as_str()));
+        Ok(())
+    }
+}
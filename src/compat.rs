@@ -0,0 +1,50 @@
+use crate::version::Version;
+
+/// maven版本要求的最低java版本，按maven版本降序
+///
+/// 参考：https://maven.apache.org/docs/history.html
+static MIN_JAVA_VERSIONS: [((u64, u64, u64), u64); 5] = [
+    ((4, 0, 0), 17),
+    ((3, 9, 0), 8),
+    ((3, 3, 1), 7),
+    ((3, 2, 1), 6),
+    ((3, 0, 0), 5),
+];
+
+/// maven版本`mvn`运行需要的最低java版本。
+/// 预发布版本与正式版本的要求相同，如`4.0.0-rc-1`要求java 17
+pub fn min_java_version(mvn: &Version) -> Option<u64> {
+    let nums = mvn.numbers();
+    let num = |i: usize| nums.get(i).copied().unwrap_or_default();
+    let ver = (num(0), num(1), num(2));
+    MIN_JAVA_VERSIONS
+        .iter()
+        .find(|(since, _)| ver >= *since)
+        .map(|(_, java)| *java)
+}
+
+/// maven版本`mvn`是否可以运行在java主版本`java`上。未知的maven版本视为可以运行
+pub fn supports_java(mvn: &Version, java: u64) -> bool {
+    min_java_version(mvn).map(|min| java >= min).unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_min_java_version() -> Result<()> {
+        assert_eq!(min_java_version(&"4.0.0-rc-1".parse()?), Some(17));
+        assert_eq!(min_java_version(&"3.9.6".parse()?), Some(8));
+        assert_eq!(min_java_version(&"3.8.4".parse()?), Some(7));
+        assert_eq!(min_java_version(&"3.3.1".parse()?), Some(7));
+        assert_eq!(min_java_version(&"3.2.5".parse()?), Some(6));
+        assert_eq!(min_java_version(&"3.0.5".parse()?), Some(5));
+        assert_eq!(min_java_version(&"2.2.1".parse()?), None);
+
+        assert!(supports_java(&"3.8.4".parse()?, 7));
+        assert!(!supports_java(&"3.9.0".parse()?, 7));
+        Ok(())
+    }
+}
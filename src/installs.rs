@@ -0,0 +1,654 @@
+use std::{
+    cell::{Ref, RefCell},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use log::{debug, trace, warn};
+
+use crate::{
+    archive::extract,
+    registry::{InstallRecord, Registry},
+    util::find_mvn_version,
+    version::{Version, VersionReq},
+};
+
+static STAGING_PREFIX: &str = ".staging-";
+static REMOVING_PREFIX: &str = ".removing-";
+static REGISTRY_FILENAME: &str = "installs.json";
+
+/// 数据目录中并存的多个maven版本。每个版本默认安装在`<data_dir>/<version>`中，
+/// 安装记录保存在`<data_dir>/installs.json`，每个实例只加载一次
+#[derive(Debug, Clone)]
+pub struct Installs {
+    data_dir: PathBuf,
+    registry: RefCell<Option<Registry>>,
+}
+
+impl Installs {
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            data_dir: data_dir.into(),
+            registry: RefCell::new(None),
+        }
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// 安装记录的副本，修改后使用[`Installs::save`]保存
+    pub fn registry(&self) -> Result<Registry> {
+        Ok(self.cached()?.clone())
+    }
+
+    /// 已加载的安装记录。记录文件还不存在时重新导入数据目录中的安装
+    fn cached(&self) -> Result<Ref<'_, Registry>> {
+        let loaded = self
+            .registry
+            .borrow()
+            .as_ref()
+            .is_some_and(Registry::exists);
+        if !loaded {
+            let registry = self.load_registry()?;
+            *self.registry.borrow_mut() = Some(registry);
+        }
+        Ok(Ref::map(self.registry.borrow(), |r| {
+            r.as_ref().expect("registry loaded")
+        }))
+    }
+
+    /// 保存安装记录并替换已加载的记录
+    pub fn save(&self, registry: Registry) -> Result<()> {
+        registry.save()?;
+        *self.registry.borrow_mut() = Some(registry);
+        Ok(())
+    }
+
+    /// 加载安装记录。没有记录文件时导入数据目录中已有的安装，
+    /// 包括旧版本安装在`<data_dir>/apache-maven-<version>`中的maven
+    fn load_registry(&self) -> Result<Registry> {
+        let mut registry = Registry::load(self.data_dir.join(REGISTRY_FILENAME))?;
+        if !registry.exists() && self.data_dir.is_dir() {
+            for entry in fs::read_dir(&self.data_dir)?.flatten() {
+                let ver = match entry.file_name().to_str().and_then(|s| {
+                    s.strip_prefix("apache-maven-")
+                        .unwrap_or(s)
+                        .parse::<Version>()
+                        .ok()
+                }) {
+                    Some(ver) => ver,
+                    None => continue,
+                };
+                if registry.get(&ver).is_some() {
+                    debug!(
+                        "skipped duplicate maven {} in {}",
+                        ver,
+                        entry.path().display()
+                    );
+                    continue;
+                }
+                let home = entry.path();
+                if !home.join("bin").join("mvn").is_file() {
+                    continue;
+                }
+                debug!("importing installed maven {} in {}", ver, home.display());
+                let installed_at = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .map(Into::into)
+                    .unwrap_or_else(|_| Local::now());
+                registry.add(InstallRecord {
+                    version: ver,
+                    url: None,
+                    filename: None,
+                    digest: None,
+                    home,
+                    links: vec![],
+                    installed_at,
+                });
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn record(&self, ver: &Version) -> Option<InstallRecord> {
+        self.cached().ok()?.get(ver).cloned()
+    }
+
+    /// 版本`ver`的maven home。未安装时为新安装的路径
+    pub fn home(&self, ver: &Version) -> PathBuf {
+        self.record(ver)
+            .map(|r| r.home)
+            .unwrap_or_else(|| self.data_dir.join(ver.to_string()))
+    }
+
+    /// 版本`ver`的mvn可执行文件
+    pub fn exe(&self, ver: &Version) -> PathBuf {
+        self.home(ver).join("bin").join("mvn")
+    }
+
+    pub fn is_installed(&self, ver: &Version) -> bool {
+        self.record(ver).map(|r| r.exe().is_file()).unwrap_or(false)
+    }
+
+    /// 已安装的版本，从新到旧排序
+    pub fn versions(&self) -> Result<Vec<Version>> {
+        Ok(self
+            .cached()?
+            .records()
+            .iter()
+            .filter(|r| r.exe().is_file())
+            .map(|r| r.version.clone())
+            .collect())
+    }
+
+    /// 查找已安装版本中与`ver_pat`匹配的最新版本
+    pub fn find(&self, ver_pat: &str) -> Result<Version> {
+        let req = ver_pat.parse::<VersionReq>()?;
+        self.versions()?
+            .into_iter()
+            .find(|ver| req.matches(ver))
+            .ok_or_else(|| anyhow!("not found installed version for {}", ver_pat))
+    }
+
+    /// 全局默认使用的版本
+    pub fn global(&self) -> Option<Version> {
+        self.cached()
+            .ok()?
+            .global()
+            .cloned()
+            .filter(|ver| self.is_installed(ver))
+    }
+
+    /// 切换全局默认版本，并记录`links`指向了该版本
+    pub fn activate(&self, ver: Option<&Version>, links: &[PathBuf]) -> Result<()> {
+        let mut registry = self.registry()?;
+        for r in registry.records_mut() {
+            r.links.retain(|l| !links.contains(l));
+            if Some(&r.version) == ver {
+                r.links.extend_from_slice(links);
+            }
+        }
+        registry.set_global(ver.cloned());
+        self.save(registry)
+    }
+
+    /// 最近一次update前使用且仍安装的版本
+    pub fn previous(&self) -> Result<Option<Version>> {
+        Ok(self
+            .cached()?
+            .previous()
+            .iter()
+            .find(|ver| self.is_installed(ver))
+            .cloned())
+    }
+
+    /// 记录版本`ver`由update安装
+    pub fn mark_updated(&self, ver: &Version) -> Result<()> {
+        let mut registry = self.registry()?;
+        registry.mark_updated(ver.clone());
+        self.save(registry)
+    }
+
+    /// 保留update前使用的版本`ver`用于rollback，返回超出保留数量`keep`且可以删除的旧版本。
+    /// 只删除由update安装的版本，不删除全局版本及与`pinned`匹配的版本
+    pub fn retain_previous(
+        &self,
+        ver: &Version,
+        keep: usize,
+        pinned: Option<&VersionReq>,
+    ) -> Result<Vec<Version>> {
+        let mut registry = self.registry()?;
+        registry.push_previous(ver.clone());
+        let outdated = registry
+            .previous()
+            .iter()
+            .skip(keep)
+            .filter(|v| {
+                registry.is_updated(v)
+                    && registry.global() != Some(*v)
+                    && !pinned.map(|req| req.matches(v)).unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        self.save(registry)?;
+        Ok(outdated)
+    }
+
+    /// rollback后不再作为旧版本保留
+    pub fn forget_previous(&self, ver: &Version) -> Result<()> {
+        let mut registry = self.registry()?;
+        registry.remove_previous(ver);
+        self.save(registry)
+    }
+
+    /// 从mvn可执行文件的路径中找出安装的版本
+    pub fn version_of(&self, exe: &Path) -> Option<Version> {
+        self.cached()
+            .ok()?
+            .records()
+            .iter()
+            .find(|r| exe.starts_with(&r.home))
+            .map(|r| r.version.clone())
+    }
+
+    pub fn register(&self, record: InstallRecord) -> Result<()> {
+        let mut registry = self.registry()?;
+        registry.add(record);
+        self.save(registry)
+    }
+
+    /// 将压缩文件`archive`解压到暂存目录后再移动到版本`ver`的maven home
+    pub fn install(&self, archive: &Path, ver: &Version) -> Result<PathBuf> {
+        let home = self.home(ver);
+        if home.exists() {
+            bail!("{} already exists", home.display());
+        }
+        self.install_with(archive, &ver.to_string(), |_| Ok(ver.clone()))
+            .map(|(_, home)| home)
+    }
+
+    /// 解压压缩文件`archive`后运行其中的`bin/mvn --version`识别版本并安装
+    pub fn install_detected(&self, archive: &Path) -> Result<(Version, PathBuf)> {
+        let name = archive
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("not found filename for {}", archive.display()))?;
+        self.install_with(archive, name, |extracted| {
+            let ver = find_mvn_version(extracted.join("bin").join("mvn"))?;
+            debug!("detected maven version {} in {}", ver, extracted.display());
+            Ok(ver)
+        })
+    }
+
+    fn install_with(
+        &self,
+        archive: &Path,
+        staging_name: &str,
+        detect: impl FnOnce(&Path) -> Result<Version>,
+    ) -> Result<(Version, PathBuf)> {
+        let staging = self
+            .data_dir
+            .join(format!("{}{}", STAGING_PREFIX, staging_name));
+        if staging.exists() {
+            debug!("removing stale staging dir {}", staging.display());
+            fs::remove_dir_all(&staging)?;
+        }
+        let res = extract(archive, staging.as_path())
+            .map_err(Into::into)
+            .and_then(|_| find_extracted_home(&staging))
+            .and_then(|extracted| {
+                let ver = detect(&extracted)?;
+                let home = self.home(&ver);
+                if home.exists() {
+                    bail!("{} already exists", home.display());
+                }
+                trace!("moving {} to {}", extracted.display(), home.display());
+                fs::rename(&extracted, &home)?;
+                Ok((ver, home))
+            });
+        if let Err(e) = fs::remove_dir_all(&staging) {
+            warn!("failed to remove staging dir {}: {}", staging.display(), e);
+        }
+        res
+    }
+
+    /// 删除maven home、指向它的链接及安装记录，返回删除的记录与实际删除的链接。
+    /// maven home先被重命名，保存安装记录失败时可恢复
+    pub fn remove(&self, ver: &Version) -> Result<(InstallRecord, Vec<PathBuf>)> {
+        let mut registry = self.registry()?;
+        let record = registry
+            .remove(ver)
+            .ok_or_else(|| anyhow!("not found installed version {}", ver))?;
+        let removing = record
+            .home
+            .with_file_name(format!("{}{}", REMOVING_PREFIX, ver));
+        if record.home.exists() {
+            trace!("moving {} to {}", record.home.display(), removing.display());
+            fs::rename(&record.home, &removing)
+                .map_err(|e| anyhow!("failed to remove {}: {}", record.home.display(), e))?;
+        }
+        if let Err(e) = self.save(registry) {
+            if removing.exists() {
+                fs::rename(&removing, &record.home)?;
+            }
+            return Err(e);
+        }
+        let mut removed_links = vec![];
+        for link in &record.links {
+            if link
+                .read_link()
+                .map(|p| p.starts_with(&record.home))
+                .unwrap_or(false)
+            {
+                debug!("removing link {}", link.display());
+                fs::remove_file(link)?;
+                removed_links.push(link.clone());
+            }
+        }
+        debug!("removing maven home {}", record.home.display());
+        if removing.exists() {
+            if let Err(e) = fs::remove_dir_all(&removing) {
+                warn!("failed to remove {}: {}", removing.display(), e);
+            }
+        }
+        Ok((record, removed_links))
+    }
+}
+
+/// 压缩文件中通常有一个`apache-maven-<version>`的顶层目录
+fn find_extracted_home(dir: &Path) -> Result<PathBuf> {
+    if dir.join("bin").join("mvn").is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let homes = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join("bin").join("mvn").is_file())
+        .collect::<Vec<_>>();
+    match homes.len() {
+        1 => Ok(homes.into_iter().next().unwrap()),
+        0 => bail!("not found bin/mvn in {}", dir.display()),
+        _ => bail!("found multiple maven homes: {:?}", homes),
+    }
+}
+
+/// 指向当前使用版本的mvn符号链接。如：`~/.local/bin/mvn`
+#[derive(Debug, Clone)]
+pub struct BinLink {
+    path: PathBuf,
+}
+
+impl BinLink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 链接的目标。`path`不存在或不是符号链接时返回None
+    pub fn target(&self) -> Option<PathBuf> {
+        self.path
+            .symlink_metadata()
+            .ok()
+            .filter(|m| m.file_type().is_symlink())
+            .and_then(|_| self.path.read_link().ok())
+    }
+
+    /// 当前使用的版本
+    pub fn active(&self, installs: &Installs) -> Option<Version> {
+        self.target().and_then(|p| installs.version_of(&p))
+    }
+
+    /// `path`存在但不是由mvnup管理
+    pub fn is_foreign(&self, installs: &Installs) -> bool {
+        self.path.symlink_metadata().is_ok() && self.active(installs).is_none()
+    }
+
+    /// 原子地将链接指向`exe`：先创建临时链接再重命名覆盖
+    #[cfg(unix)]
+    pub fn point_to(&self, exe: &Path) -> Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .ok_or_else(|| anyhow!("not found parent dir for {}", self.path.display()))?;
+        fs::create_dir_all(dir)?;
+        let tmp = dir.join(format!(".mvn.{}.tmp", std::process::id()));
+        if tmp.symlink_metadata().is_ok() {
+            fs::remove_file(&tmp)?;
+        }
+        std::os::unix::fs::symlink(exe, &tmp)?;
+        fs::rename(&tmp, &self.path).map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            anyhow!("failed to link {}: {}", self.path.display(), e)
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn point_to(&self, _exe: &Path) -> Result<()> {
+        bail!("unsupported to link {}", self.path.display())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn create_archive(dir: &Path, ver: &str) -> Result<PathBuf> {
+        use std::io::Write;
+        let path = dir.join(format!("apache-maven-{}-bin.zip", ver));
+        let mut zip = ZipWriter::new(File::create(&path)?);
+        zip.start_file(
+            format!("apache-maven-{}/bin/mvn", ver),
+            FileOptions::default().unix_permissions(0o755),
+        )?;
+        zip.write_all(b"#!/bin/sh\n")?;
+        zip.finish()?;
+        Ok(path)
+    }
+
+    #[test]
+    fn test_install_side_by_side() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        for ver in ["3.6.3", "3.8.4"] {
+            install(&installs, dir.path(), ver)?;
+        }
+        assert_eq!(
+            installs.versions()?,
+            vec!["3.8.4".parse::<Version>()?, "3.6.3".parse()?]
+        );
+        assert_eq!(installs.find("~3.6")?, "3.6.3".parse()?);
+        assert!(installs.find("~3.9").is_err());
+        assert_eq!(installs.find("3.6.3")?, "3.6.3".parse()?);
+        assert_eq!(installs.find("3")?, "3.8.4".parse()?);
+
+        assert_eq!(installs.global(), None);
+        installs.activate(Some(&"3.6.3".parse()?), &[])?;
+        assert_eq!(installs.global(), Some("3.6.3".parse()?));
+        installs.activate(None, &[])?;
+        assert_eq!(installs.global(), None);
+
+        // already installed
+        let archive = create_archive(dir.path(), "3.8.4")?;
+        assert!(installs.install(&archive, &"3.8.4".parse()?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_detected() -> Result<()> {
+        use std::io::Write;
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        fs::create_dir_all(installs.data_dir())?;
+        let path = dir.path().join("vetted-maven.zip");
+        let mut zip = ZipWriter::new(File::create(&path)?);
+        zip.start_file(
+            "maven/bin/mvn",
+            FileOptions::default().unix_permissions(0o755),
+        )?;
+        zip.write_all(
+            b"#!/bin/sh\necho 'Apache Maven 3.9.1 (2e178502fcdbffc201671fb2537d0cb4b4cc58f8)'\n",
+        )?;
+        zip.finish()?;
+
+        let (ver, home) = installs.install_detected(&path)?;
+        assert_eq!(ver, "3.9.1".parse()?);
+        assert_eq!(home, installs.data_dir().join("3.9.1"));
+        assert!(home.join("bin").join("mvn").is_file());
+        // already installed
+        assert!(installs.install_detected(&path).is_err());
+        assert_eq!(fs::read_dir(installs.data_dir())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retain_previous() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        for ver in ["3.6.3", "3.8.1", "3.8.4"] {
+            install(&installs, dir.path(), ver)?;
+        }
+        let (v1, v2) = ("3.6.3".parse::<Version>()?, "3.8.1".parse::<Version>()?);
+        installs.mark_updated(&v1)?;
+        installs.mark_updated(&v2)?;
+        assert_eq!(installs.previous()?, None);
+        assert!(installs.retain_previous(&v1, 1, None)?.is_empty());
+        assert_eq!(installs.retain_previous(&v2, 1, None)?, vec![v1.clone()]);
+        assert_eq!(installs.previous()?, Some(v2.clone()));
+
+        // 不删除pin文件或全局使用的版本
+        assert!(installs
+            .retain_previous(&v2, 1, Some(&"~3.6".parse()?))?
+            .is_empty());
+        installs.activate(Some(&v1), &[])?;
+        assert!(installs.retain_previous(&v2, 1, None)?.is_empty());
+        installs.activate(None, &[])?;
+
+        installs.remove(&v1)?;
+        installs.forget_previous(&v2)?;
+        assert_eq!(installs.previous()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retain_previous_keeps_manual_installs() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        for ver in ["3.6.3", "3.8.1", "3.8.4"] {
+            install(&installs, dir.path(), ver)?;
+        }
+        let (v1, v2, v3) = (
+            "3.6.3".parse::<Version>()?,
+            "3.8.1".parse::<Version>()?,
+            "3.8.4".parse::<Version>()?,
+        );
+        // 3.6.3由install安装，3.8.1由update安装
+        installs.mark_updated(&v2)?;
+        installs.retain_previous(&v1, 0, None)?;
+        installs.retain_previous(&v2, 0, None)?;
+        assert_eq!(installs.retain_previous(&v3, 0, None)?, vec![v2]);
+        Ok(())
+    }
+
+    fn install(installs: &Installs, dir: &Path, ver: &str) -> Result<()> {
+        let archive = create_archive(dir, ver)?;
+        let home = installs.install(&archive, &ver.parse()?)?;
+        assert_eq!(home, installs.data_dir().join(ver));
+        installs.register(InstallRecord {
+            version: ver.parse()?,
+            url: None,
+            filename: archive
+                .file_name()
+                .and_then(|s| s.to_str())
+                .map(ToString::to_string),
+            digest: None,
+            home,
+            links: vec![],
+            installed_at: Local::now(),
+        })
+    }
+
+    #[test]
+    fn test_import_existing_installs() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        let archive = create_archive(dir.path(), "3.8.4")?;
+        installs.install(&archive, &"3.8.4".parse()?)?;
+
+        assert!(!installs.registry()?.exists());
+        assert_eq!(installs.versions()?, vec!["3.8.4".parse::<Version>()?]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry_loaded_once() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        install(&installs, dir.path(), "3.8.4")?;
+        let path = installs.data_dir().join(REGISTRY_FILENAME);
+        fs::write(&path, "{")?;
+        assert_eq!(installs.versions()?, vec!["3.8.4".parse::<Version>()?]);
+        installs.activate(Some(&"3.8.4".parse()?), &[])?;
+        assert_eq!(installs.global(), Some("3.8.4".parse()?));
+
+        // 保存的记录对新的实例可见
+        let reloaded = Installs::new(installs.data_dir());
+        assert_eq!(reloaded.global(), Some("3.8.4".parse()?));
+        Ok(())
+    }
+
+    /// 旧版本安装在`<data_dir>/apache-maven-<version>`，并链接`~/.local/bin/mvn`
+    #[cfg(unix)]
+    #[test]
+    fn test_import_legacy_installs() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        let home = installs.data_dir().join("apache-maven-3.8.4");
+        fs::create_dir_all(home.join("bin"))?;
+        fs::write(home.join("bin").join("mvn"), "#!/bin/sh\n")?;
+        let link = BinLink::new(dir.path().join("bin").join("mvn"));
+        link.point_to(&home.join("bin").join("mvn"))?;
+
+        let ver = "3.8.4".parse::<Version>()?;
+        assert_eq!(installs.versions()?, vec![ver.clone()]);
+        assert_eq!(installs.home(&ver), home);
+        assert!(installs.is_installed(&ver));
+        assert_eq!(link.active(&installs), Some(ver.clone()));
+        assert!(!link.is_foreign(&installs));
+
+        installs.remove(&ver)?;
+        assert!(!home.exists());
+        assert!(installs.versions()?.is_empty());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bin_link() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        let link = BinLink::new(dir.path().join("bin").join("mvn"));
+        assert_eq!(link.active(&installs), None);
+
+        install(&installs, dir.path(), "3.6.3")?;
+        install(&installs, dir.path(), "3.8.4")?;
+        let (v1, v2) = ("3.6.3".parse()?, "3.8.4".parse()?);
+        link.point_to(&installs.exe(&v1))?;
+        assert_eq!(link.active(&installs), Some(v1.clone()));
+        link.point_to(&installs.exe(&v2))?;
+        assert_eq!(link.active(&installs), Some(v2.clone()));
+        assert!(!link.is_foreign(&installs));
+
+        // 删除版本时同时删除记录的链接，不删除已指向其它位置的链接
+        let other = BinLink::new(dir.path().join("bin").join("mvn-other"));
+        other.point_to(&installs.exe(&v2))?;
+        installs.activate(
+            Some(&v2),
+            &[link.path().to_path_buf(), other.path().to_path_buf()],
+        )?;
+        other.point_to(&installs.exe(&v1))?;
+        let (_, removed) = installs.remove(&v2)?;
+        assert_eq!(removed, vec![link.path().to_path_buf()]);
+        assert_eq!(other.active(&installs), Some(v1));
+        assert!(link.path().symlink_metadata().is_err());
+        assert!(!installs.home(&v2).exists());
+        assert_eq!(std::fs::read_dir(installs.data_dir())?.count(), 2);
+
+        std::fs::write(link.path(), "")?;
+        assert!(link.is_foreign(&installs));
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    cmp::Ordering,
+    env,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Error, Result};
+use getset::Getters;
+use log::{debug, trace};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"version "([^"]+)""#).unwrap());
+
+/// 如`1.8.0_312-b07`、`17.0.1-ea+12`
+static NUMBER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"^(\d+)(?:\.(\d+))?(?:\.(\d+))?(?:\.(\d+))?(?:_(\d+))?",
+        r"(?:-b(\d+))?(?:-([0-9A-Za-z.]+))?(?:\+(\d+))?"
+    ))
+    .unwrap()
+});
+
+/// 匹配`(build ...)`前的名称与build版本
+static BUILD_LINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+?)\s*\(build ([^,)]+)").unwrap());
+
+/// 运行时名称中出现的发行商
+static VENDORS: [(&str, &str); 9] = [
+    ("Temurin", "Eclipse Adoptium"),
+    ("AdoptOpenJDK", "AdoptOpenJDK"),
+    ("Zulu", "Azul"),
+    ("Corretto", "Amazon"),
+    ("GraalVM", "GraalVM"),
+    ("Microsoft", "Microsoft"),
+    ("Semeru", "IBM"),
+    ("JBR", "JetBrains"),
+    ("Java(TM)", "Oracle"),
+];
+
+/// `java -version`输出的java版本。旧的`1.x`版本号被规范为feature版本，
+/// 如`1.8.0_312`的feature为8，update为312
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct JavaVersion {
+    /// 原始的版本号，如`1.8.0_312`
+    raw: String,
+    feature: u64,
+    interim: u64,
+    update: u64,
+    patch: u64,
+    /// 预发布标识，如`ea`
+    pre: Option<String>,
+    build: Option<u64>,
+    vendor: Option<String>,
+    /// 虚拟机名称，如`OpenJDK 64-Bit Server VM`
+    vm: Option<String>,
+}
+
+impl JavaVersion {
+    /// 从版本号解析，不包含发行商与虚拟机信息
+    pub fn parse_number(raw: &str) -> Result<Self> {
+        let caps = NUMBER_RE
+            .captures(raw)
+            .ok_or_else(|| anyhow!("invalid java version: {}", raw))?;
+        let num = |i: usize| -> Result<u64> {
+            caps.get(i)
+                .map(|m| m.as_str().parse::<u64>())
+                .transpose()
+                .map(Option::unwrap_or_default)
+                .map_err(Into::into)
+        };
+        let (mut feature, mut interim, mut update, mut patch) =
+            (num(1)?, num(2)?, num(3)?, num(4)?);
+        if feature == 1 && caps.get(2).is_some() {
+            // 1.8.0_312 -> 8.0.312
+            feature = interim;
+            interim = 0;
+            update = num(5)?;
+            patch = 0;
+        }
+        let build = caps
+            .get(6)
+            .or_else(|| caps.get(8))
+            .map(|m| m.as_str().parse::<u64>())
+            .transpose()?;
+        Ok(Self {
+            raw: raw.to_string(),
+            feature,
+            interim,
+            update,
+            patch,
+            pre: caps.get(7).map(|m| m.as_str().to_string()),
+            build,
+            vendor: None,
+            vm: None,
+        })
+    }
+}
+
+impl FromStr for JavaVersion {
+    type Err = Error;
+
+    /// 解析`java -version`的输出
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = VERSION_RE
+            .captures(s)
+            .and_then(|caps| caps.get(1))
+            .ok_or_else(|| anyhow!("not found java version in: {}", s))?
+            .as_str();
+        let mut ver = Self::parse_number(raw)?;
+        let mut lines = s
+            .lines()
+            .skip_while(|l| !VERSION_RE.is_match(l))
+            .skip(1)
+            .filter_map(|l| BUILD_LINE_RE.captures(l.trim()));
+        if let Some(runtime) = lines.next() {
+            let name = &runtime[1];
+            trace!("found java runtime {}", name);
+            ver.vendor = VENDORS
+                .iter()
+                .find(|(key, _)| name.contains(key))
+                .map(|(_, vendor)| vendor.to_string())
+                .or_else(|| name.starts_with("OpenJDK").then(|| "OpenJDK".to_string()));
+            // 版本号中没有build时使用运行时的build
+            if ver.build.is_none() {
+                ver.build = Self::parse_number(&runtime[2]).ok().and_then(|v| v.build);
+            }
+        }
+        ver.vm = lines.next().map(|vm| vm[1].to_string());
+        Ok(ver)
+    }
+}
+
+impl PartialEq for JavaVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for JavaVersion {}
+
+impl PartialOrd for JavaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JavaVersion {
+    /// 预发布版本小于正式版本。不比较发行商与虚拟机
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.feature, self.interim, self.update, self.patch)
+            .cmp(&(other.feature, other.interim, other.update, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl Display for JavaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)?;
+        if let Some(vendor) = &self.vendor {
+            write!(f, " ({})", vendor)?;
+        }
+        Ok(())
+    }
+}
+
+/// 与mvn启动脚本一致，优先使用`JAVA_HOME`中的java
+pub fn find_java() -> Result<PathBuf> {
+    match env::var_os("JAVA_HOME").filter(|s| !s.is_empty()) {
+        Some(home) => {
+            let exe = java_exe(Path::new(&home));
+            debug!("using java {} from JAVA_HOME", exe.display());
+            if !exe.is_file() {
+                bail!(
+                    "JAVA_HOME is not defined correctly: not found {}",
+                    exe.display()
+                );
+            }
+            Ok(exe)
+        }
+        None => which::which("java").map_err(|e| anyhow!("not found java in PATH: {}", e)),
+    }
+}
+
+fn java_exe(home: &Path) -> PathBuf {
+    let name = if cfg!(windows) { "java.exe" } else { "java" };
+    home.join("bin").join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let ver = r#"openjdk version "17.0.1" 2021-10-19
+OpenJDK Runtime Environment Temurin-17.0.1+12 (build 17.0.1+12)
+OpenJDK 64-Bit Server VM Temurin-17.0.1+12 (build 17.0.1+12, mixed mode, sharing)"#
+            .parse::<JavaVersion>()?;
+        assert_eq!(*ver.feature(), 17);
+        assert_eq!(*ver.update(), 1);
+        assert_eq!(*ver.build(), Some(12));
+        assert_eq!(ver.vendor().as_deref(), Some("Eclipse Adoptium"));
+        assert_eq!(
+            ver.vm().as_deref(),
+            Some("OpenJDK 64-Bit Server VM Temurin-17.0.1+12")
+        );
+
+        let ver = r#"java version "1.8.0_312"
+Java(TM) SE Runtime Environment (build 1.8.0_312-b07)
+Java HotSpot(TM) 64-Bit Server VM (build 25.312-b07, mixed mode)"#
+            .parse::<JavaVersion>()?;
+        assert_eq!(*ver.feature(), 8);
+        assert_eq!(*ver.update(), 312);
+        assert_eq!(*ver.build(), Some(7));
+        assert_eq!(ver.vendor().as_deref(), Some("Oracle"));
+        assert_eq!(
+            ver.vm().as_deref(),
+            Some("Java HotSpot(TM) 64-Bit Server VM")
+        );
+
+        // 输出前可能有JAVA_TOOL_OPTIONS等提示
+        let ver = "Picked up JAVA_TOOL_OPTIONS: -Dfile.encoding=UTF8\nopenjdk version \"21-ea\" 2023-09-19\n"
+            .parse::<JavaVersion>()?;
+        assert_eq!(*ver.feature(), 21);
+        assert_eq!(ver.pre().as_deref(), Some("ea"));
+        assert_eq!(*ver.vendor(), None);
+
+        assert!("no java".parse::<JavaVersion>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordering() -> Result<()> {
+        let vers = [
+            "1.7.0_80",
+            "1.8.0_312",
+            "11.0.13",
+            "17-ea",
+            "17",
+            "17.0.1+12",
+        ]
+        .iter()
+        .map(|s| JavaVersion::parse_number(s))
+        .collect::<Result<Vec<_>>>()?;
+        assert!(vers.windows(2).all(|w| w[0] < w[1]), "{:?}", vers);
+        assert_eq!(
+            JavaVersion::parse_number("1.8.0")?,
+            JavaVersion::parse_number("8")?
+        );
+        Ok(())
+    }
+}
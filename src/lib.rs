@@ -1,5 +1,23 @@
+pub mod archive;
+pub mod auth;
+pub mod bench;
+pub mod closer;
+pub mod compat;
+pub mod config;
+pub mod http;
+pub mod installs;
+pub mod java;
+pub mod listing;
+pub mod pin;
+pub mod registry;
+pub mod repository;
+pub mod settings;
+pub mod shim;
+pub mod signature;
 pub mod site;
+pub mod source;
 pub mod util;
+pub mod version;
 
 pub static CRATE_NAME: &str = env!("CARGO_PKG_NAME");
 
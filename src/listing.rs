@@ -0,0 +1,283 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use getset::Getters;
+use log::{debug, trace};
+use scraper::{Html, Selector};
+use url::Url;
+
+/// 目录列表页面的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingFormat {
+    /// apache httpd的mod_autoindex，如：https://archive.apache.org/dist/
+    Apache,
+    /// nginx的autoindex
+    Nginx,
+    /// lighttpd的mod_dirlisting
+    Lighttpd,
+    /// S3兼容对象存储的`ListBucketResult`
+    S3,
+}
+
+impl ListingFormat {
+    /// 从页面内容识别格式。无法识别的html按nginx的链接格式解析
+    pub fn detect(content: &str) -> Self {
+        let head = content.trim_start();
+        if head.starts_with("<?xml") || head.starts_with("<ListBucketResult") {
+            ListingFormat::S3
+        } else if content.contains("summary=\"Directory Listing\"") {
+            ListingFormat::Lighttpd
+        } else if content.contains("alt=\"[DIR]\"") || content.contains("alt=\"[PARENTDIR]\"") {
+            ListingFormat::Apache
+        } else {
+            ListingFormat::Nginx
+        }
+    }
+}
+
+/// 目录列表中的文件或子目录
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct Entry {
+    name: String,
+    dir: bool,
+}
+
+/// 解析目录列表页面中的所有条目，不包含上级目录
+pub fn parse_entries(content: &str) -> Result<Vec<Entry>> {
+    let format = ListingFormat::detect(content);
+    debug!(
+        "parsing {:?} listing in content size: {}",
+        format,
+        content.len()
+    );
+    let entries = match format {
+        ListingFormat::S3 => parse_s3(content)?,
+        ListingFormat::Apache => parse_html(content, "img+a[href]")?,
+        ListingFormat::Lighttpd => parse_html(content, "td.n>a[href]")?,
+        ListingFormat::Nginx => parse_html(content, "a[href]")?,
+    };
+    trace!("found {} entries: {:?}", entries.len(), entries);
+    Ok(entries)
+}
+
+/// 从html链接的href中解析条目。忽略排序参数、`.`与`..`及其它站点的链接，保留`.`开头的文件。
+/// 不使用链接文本，nginx会截断过长的文件名
+fn parse_html(content: &str, selector: &str) -> Result<Vec<Entry>> {
+    let html = Html::parse_document(content);
+    let selector = Selector::parse(selector).map_err(|e| {
+        anyhow!(
+            "failed to parsing. kind: {:?}, location: {:?}",
+            e.kind,
+            e.location
+        )
+    })?;
+    let entries = html
+        .select(&selector)
+        .flat_map(|e| e.value().attr("href"))
+        .flat_map(|href| {
+            let href = href.trim().trim_start_matches("./");
+            if href.is_empty() || href.starts_with(['?', '#', '/']) || Url::parse(href).is_ok() {
+                return None;
+            }
+            let name = href.trim_end_matches('/');
+            (!matches!(name, "" | "." | "..") && !name.contains('/')).then(|| Entry {
+                name: name.to_string(),
+                dir: href.ends_with('/'),
+            })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// 解析S3的`ListBucketResult`。`CommonPrefixes`为子目录，`Contents`为文件
+fn parse_s3(content: &str) -> Result<Vec<Entry>> {
+    let doc = roxmltree::Document::parse(content)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("ListBucketResult") {
+        bail!("unsupported xml listing: {}", root.tag_name().name());
+    }
+    let child_text = |node: roxmltree::Node, name: &str| {
+        node.children()
+            .find(|n| n.has_tag_name(name))
+            .and_then(|n| n.text())
+            .map(|s| s.to_string())
+    };
+    let entries = root
+        .children()
+        .flat_map(|node| {
+            if node.has_tag_name("CommonPrefixes") {
+                child_text(node, "Prefix").map(|key| (key, true))
+            } else if node.has_tag_name("Contents") {
+                child_text(node, "Key").map(|key| (key, false))
+            } else {
+                None
+            }
+        })
+        .flat_map(|(key, dir)| {
+            key.trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(|name| Entry {
+                    name: name.to_string(),
+                    dir,
+                })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// 读取本地目录`path`中的条目，按名称排序。跟随符号链接判断是否为目录
+pub fn read_dir(path: impl AsRef<Path>) -> Result<Vec<Entry>> {
+    let path = path.as_ref();
+    trace!("reading local listing {}", path.display());
+    let mut entries = fs::read_dir(path)
+        .map_err(|e| anyhow!("failed to read dir {}: {}", path.display(), e))?
+        .map(|entry| {
+            let entry = entry?;
+            Ok(Entry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                dir: entry.path().is_dir(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// S3不支持直接访问目录，使用`ListObjectsV2`请求列出`dir_url`中的条目。
+/// 只支持虚拟主机风格的bucket地址，如：https://bucket.s3.amazonaws.com/maven/
+pub fn s3_listing_url(dir_url: &Url) -> Result<Url> {
+    let prefix = dir_url.path().trim_start_matches('/');
+    let mut url = dir_url.join("/")?;
+    url.query_pairs_mut()
+        .append_pair("list-type", "2")
+        .append_pair("delimiter", "/")
+        .append_pair("prefix", prefix);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(entries: &[Entry], dir: bool) -> Vec<&str> {
+        entries
+            .iter()
+            .filter(|e| e.dir == dir)
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_apache() -> Result<()> {
+        let content = r#"<html><body><h1>Index of /dist/maven/maven-3/3.8.4/binaries</h1>
+<pre><img src="/icons/blank.gif" alt="Icon "> <a href="?C=N;O=D">Name</a>                                 <a href="?C=M;O=A">Last modified</a><hr><img src="/icons/back.gif" alt="[PARENTDIR]"> <a href="/dist/maven/maven-3/3.8.4/">Parent Directory</a>                                          -
+<img src="/icons/folder.gif" alt="[DIR]"> <a href="old/">old/</a>                  2012-09-11 09:37    -
+<img src="/icons/compressed.gif" alt="[   ]"> <a href="apache-maven-3.8.4-bin.tar.gz">apache-maven-3.8.4-bin.tar.gz</a>        2021-11-14 13:25  8.6M
+<img src="/icons/text.gif" alt="[TXT]"> <a href="apache-maven-3.8.4-bin.tar.gz.asc">apache-maven-3.8.4-bin.tar.gz.asc</a>    2021-11-14 13:25  484
+<hr></pre></body></html>"#;
+        assert_eq!(ListingFormat::detect(content), ListingFormat::Apache);
+        let entries = parse_entries(content)?;
+        assert_eq!(names(&entries, true), vec!["old"]);
+        assert_eq!(
+            names(&entries, false),
+            vec![
+                "apache-maven-3.8.4-bin.tar.gz",
+                "apache-maven-3.8.4-bin.tar.gz.asc"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nginx() -> Result<()> {
+        let content = r#"<html>
+<head><title>Index of /maven/maven-3/3.8.4/binaries/</title></head>
+<body>
+<h1>Index of /maven/maven-3/3.8.4/binaries/</h1><hr><pre><a href="../">../</a>
+<a href="old/">old/</a>                                               14-Nov-2021 13:25                   -
+<a href=".">.</a>
+<a href=".mirror-info">.mirror-info</a>                                       14-Nov-2021 13:25                  42
+<a href="apache-maven-3.8.4-bin.tar.gz">apache-maven-3.8.4-bin.tar.gz</a>                      14-Nov-2021 13:25             9046177
+<a href="apache-maven-3.8.4-bin.tar.gz.sha512">apache-maven-3.8.4-bin.tar.gz.sha512</a>               14-Nov-2021 13:25                 128
+<a href="apache-maven-3.8.4-bin.tar.gz.sha512.longname">apache-maven-3.8.4-bin.tar.gz.sha512.lon..&gt;</a> 14-Nov-2021 13:25                 128
+</pre><hr></body>
+</html>"#;
+        assert_eq!(ListingFormat::detect(content), ListingFormat::Nginx);
+        let entries = parse_entries(content)?;
+        assert_eq!(names(&entries, true), vec!["old"]);
+        assert_eq!(
+            names(&entries, false),
+            vec![
+                ".mirror-info",
+                "apache-maven-3.8.4-bin.tar.gz",
+                "apache-maven-3.8.4-bin.tar.gz.sha512",
+                "apache-maven-3.8.4-bin.tar.gz.sha512.longname"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_lighttpd() -> Result<()> {
+        let content = r#"<!DOCTYPE html>
+<html><head><title>Index of /maven/maven-3/</title></head>
+<body>
+<h2>Index of /maven/maven-3/</h2>
+<div class="list">
+<table summary="Directory Listing" cellpadding="0" cellspacing="0">
+<thead><tr><th class="n">Name</th><th class="m">Last Modified</th><th class="s">Size</th><th class="t">Type</th></tr></thead>
+<tbody>
+<tr class="d"><td class="n"><a href="../">Parent Directory</a>/</td><td class="m">&nbsp;</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr class="d"><td class="n"><a href="3.6.3/">3.6.3</a>/</td><td class="m">2020-Jul-03 04:01:00</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr class="d"><td class="n"><a href="3.8.4/">3.8.4</a>/</td><td class="m">2021-Nov-20 14:43:00</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr><td class="n"><a href="README.txt">README.txt</a></td><td class="m">2021-Nov-20 14:43:00</td><td class="s">0.1K</td><td class="t">text/plain</td></tr>
+</tbody>
+</table>
+</div>
+<div class="foot"><a href="https://www.lighttpd.net/">lighttpd/1.4.59</a></div>
+</body></html>"#;
+        assert_eq!(ListingFormat::detect(content), ListingFormat::Lighttpd);
+        let entries = parse_entries(content)?;
+        assert_eq!(names(&entries, true), vec!["3.6.3", "3.8.4"]);
+        assert_eq!(names(&entries, false), vec!["README.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_s3() -> Result<()> {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>mirror</Name>
+  <Prefix>maven/maven-3/</Prefix>
+  <KeyCount>3</KeyCount>
+  <Delimiter>/</Delimiter>
+  <IsTruncated>false</IsTruncated>
+  <Contents>
+    <Key>maven/maven-3/README.txt</Key>
+    <Size>120</Size>
+  </Contents>
+  <CommonPrefixes><Prefix>maven/maven-3/3.6.3/</Prefix></CommonPrefixes>
+  <CommonPrefixes><Prefix>maven/maven-3/3.8.4/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+        assert_eq!(ListingFormat::detect(content), ListingFormat::S3);
+        let entries = parse_entries(content)?;
+        assert_eq!(names(&entries, true), vec!["3.6.3", "3.8.4"]);
+        assert_eq!(names(&entries, false), vec!["README.txt"]);
+
+        assert!(parse_entries("<?xml version=\"1.0\"?><Error></Error>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_s3_listing_url() -> Result<()> {
+        let url = s3_listing_url(&"https://bucket.s3.amazonaws.com/maven/maven-3/".parse()?)?;
+        assert_eq!(
+            url.as_str(),
+            "https://bucket.s3.amazonaws.com/?list-type=2&delimiter=%2F&prefix=maven%2Fmaven-3%2F"
+        );
+        Ok(())
+    }
+}
@@ -1,27 +1,46 @@
 use std::{
-    collections::HashSet,
-    fs::{remove_dir_all, remove_file},
-    path::PathBuf,
+    ffi::OsString,
+    fs::remove_file,
+    path::{Path, PathBuf},
     process::exit,
     sync::Arc,
 };
 
-use anyhow::{anyhow, bail, Error, Result};
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
 use comfy_table::Table;
 use directories::{BaseDirs, ProjectDirs};
 use futures_util::{future::join_all, try_join};
-use glob::glob;
-use log::{debug, info, trace, warn};
+use log::{debug, error, info, trace, warn};
 use mvnup::{
-    site::{BinFile, Site},
-    util::{extract, find_java_version, find_mvn_version, match_digests},
+    archive::ArchiveFormat,
+    auth::{take_userinfo, Auths, Credentials},
+    bench::{bench, rank},
+    closer::{discover_mirrors, ARCHIVE_URL},
+    compat::{min_java_version, supports_java},
+    config::{Config, CONFIG_FILENAME},
+    http,
+    installs::{BinLink, Installs},
+    java::{find_java, JavaVersion},
+    pin::{Pin, PIN_FILENAME},
+    registry::{InstallRecord, Registry},
+    settings::Settings,
+    shim::Shims,
+    signature::Keyring,
+    site::{fetch_digest, parse_digest, BinFile, Site},
+    source::{merge_mirrors, Layout, Mirror, Mirrors, Source},
+    util::{
+        find_java_version, find_mvn_version, get_filename, match_digests, match_versions,
+        parse_archive_version, parse_location, parse_mirror,
+    },
+    version::{Channel, Version, VersionReq},
     CRATE_NAME,
 };
-use semver::{Version, VersionReq};
+use once_cell::sync::OnceCell;
 use structopt::StructOpt;
+use strum::VariantNames;
 use tokio::fs as afs;
 use tokio::sync::Mutex;
-use url::Url;
 use which::which;
 
 #[tokio::main]
@@ -34,12 +53,61 @@ async fn main() -> Result<()> {
 
 #[derive(Debug, StructOpt, Clone)]
 pub struct Opt {
-    #[structopt(long, short, default_value = "https://archive.apache.org/dist/")]
-    mirror: Url,
+    /// mirror url, local directory such as a `file:///srv/apache-dist/` mount or `auto`
+    /// to select the nearest apache mirror and fall back to the archive for old versions.
+    /// repeat it to fail over to the next mirror, followed by `mirrors` in config.
+    /// default is https://archive.apache.org/dist/
+    #[structopt(long, short, number_of_values = 1)]
+    mirror: Vec<Mirror>,
+
+    /// directory layout of the mirror: `dist` for apache dist sites or
+    /// `repository` for maven repositories such as https://repo.maven.apache.org/maven2/
+    #[structopt(long, default_value = "dist", possible_values = Layout::VARIANTS)]
+    layout: Layout,
 
     #[structopt(long, short, parse(from_occurrences))]
     verbose: u8,
 
+    /// verify the OpenPGP signature of downloaded archives with maven `KEYS`
+    #[structopt(long)]
+    verify_signature: bool,
+
+    /// use a local `KEYS` file as keyring instead of fetching it from mirror.
+    /// implies `--verify-signature`
+    #[structopt(long, parse(from_os_str))]
+    keys: Option<PathBuf>,
+
+    /// release channel of candidate versions: stable, rc, milestone, beta or alpha.
+    /// a channel also includes more stable versions
+    #[structopt(long, possible_values = Channel::VARIANTS)]
+    channel: Option<Channel>,
+
+    /// include all pre-releases. same as `--channel alpha`
+    #[structopt(long, conflicts_with = "channel")]
+    pre: bool,
+
+    /// proxy url for all requests such as `http://proxy.example.com:3128`.
+    /// overrides `proxy` in the `[http]` config. hosts in `NO_PROXY` are not proxied
+    #[structopt(long)]
+    proxy: Option<String>,
+
+    /// additional trusted CA certificates in a PEM file, such as the root
+    /// certificate of a TLS intercepting proxy. overrides `ca-cert` in the `[http]` config
+    #[structopt(long, parse(from_os_str))]
+    ca_cert: Option<PathBuf>,
+
+    /// also use `<mirrors>` and the active `<proxy>` in the maven `settings.xml` of the user
+    /// (`~/.m2/settings.xml`) and the maven installation (`$MAVEN_HOME/conf/settings.xml`).
+    /// the mirrors follow `--mirror` and config `mirrors`, using `<server>` with the same id
+    /// for authentication. mirrors of `central` or `*` are used with the repository layout.
+    /// the proxy is used unless `--proxy` or `[http]` config is set
+    #[structopt(long)]
+    maven_settings: bool,
+
+    /// config file. default is `config.toml` in the config dir
+    #[structopt(long, short, parse(from_os_str))]
+    config: Option<PathBuf>,
+
     #[structopt(subcommand)]
     commands: Option<Commands>,
 }
@@ -62,54 +130,227 @@ impl Opt {
 #[derive(Debug, StructOpt, Clone)]
 enum Commands {
     Install {
-        #[structopt(long, short)]
+        #[structopt(long, short, conflicts_with = "from")]
         version: Option<String>,
+
+        /// install a local file or url of `apache-maven-*-bin.tar.gz` or `.zip`
+        /// instead of a version from the mirror
+        #[structopt(long)]
+        from: Option<String>,
+
+        /// expected sha512 of the `--from` archive. default is its `.sha512` sidecar file
+        #[structopt(long, requires = "from")]
+        sha512: Option<String>,
+
+        /// install the `--from` archive without verifying its digest
+        #[structopt(long, requires = "from", conflicts_with = "sha512")]
+        no_verify: bool,
     },
     Update {
         version: Option<String>,
     },
-    Uninstall,
+    /// show the maven version used in current dir
+    Current,
+    /// switch the active maven to an installed version
+    Use {
+        version: String,
+    },
+    /// replace the mvn link with shims that select the version by current dir
+    Shim {
+        /// remove shims and link mvn to the global version
+        #[structopt(long)]
+        disable: bool,
+    },
+    /// run a maven command with the version selected by current dir
+    Exec {
+        command: String,
+        #[structopt(parse(from_os_str))]
+        args: Vec<OsString>,
+    },
+    /// switch back to the version used before the last update
+    Rollback,
+    /// uninstall a version. default is the active version
+    Uninstall {
+        version: Option<String>,
+    },
     List {
         #[structopt(long, short, default_value = "5")]
         limit: usize,
+
+        /// list installed versions
+        #[structopt(long, short)]
+        installed: bool,
+    },
+    /// manage mirrors
+    Mirrors(MirrorsCommand),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+enum MirrorsCommand {
+    /// measure the index latency and download speed of mirrors and rank them
+    Bench {
+        /// also bench mirrors discovered by the apache mirror selector
+        #[structopt(long)]
+        discover: bool,
+
+        /// bytes of the latest binary to download from each mirror
+        #[structopt(long, default_value = "1048576")]
+        bytes: u64,
+
+        /// write the fastest mirror into config as the first of `mirrors`
+        #[structopt(long)]
+        save: bool,
     },
 }
 
 struct Program {
     opt: Opt,
+    config: Config,
+    config_path: PathBuf,
+    /// 命令行与配置中的镜像，按使用顺序
+    mirrors: Vec<(Mirror, Layout)>,
     manager: Manager,
-    base_dir: BaseDirs,
-    project_dirs: ProjectDirs,
+    installs: Installs,
+    link: Option<BinLink>,
+    shims: Option<Shims>,
+    /// 当前目录的pin文件，只在install、check、current与exec中查找
+    pin: OnceCell<Option<Pin>>,
 }
 
 impl Program {
     pub fn new(opt: Opt) -> Result<Self> {
         let base_dir = BaseDirs::new().ok_or_else(|| anyhow!("not found base dir"))?;
+        let project_dirs = ProjectDirs::from("xyz", "navyd", CRATE_NAME)
+            .ok_or_else(|| anyhow!("project dir error"))?;
+        let verify_keys = match &opt.keys {
+            Some(path) => Some(KeysSource::Local(path.clone())),
+            None if opt.verify_signature => Some(KeysSource::Mirror),
+            None => None,
+        };
+        let config_path = opt
+            .config
+            .clone()
+            .unwrap_or_else(|| project_dirs.config_dir().join(CONFIG_FILENAME));
+        let config = Config::load(&config_path)?;
+        let mut http_config = config.http().clone();
+        if opt.proxy.is_some() {
+            http_config.set_proxy(opt.proxy.clone());
+        }
+        if opt.ca_cert.is_some() {
+            http_config.set_ca_cert(opt.ca_cert.clone());
+        }
+        let channel = if opt.pre {
+            Channel::Alpha
+        } else {
+            opt.channel.unwrap_or(*config.channel())
+        };
+        debug!("using {} channel", channel);
+        let maven_settings = opt.maven_settings || *config.maven_settings();
+        let settings = if maven_settings || !config.auth().is_empty() {
+            Settings::load_all()?
+        } else {
+            Settings::default()
+        };
+        let mut auths = Auths::default();
+        for (id, auth) in config.auth() {
+            match auth.credentials(id, &settings) {
+                Some(creds) => auths.push(auth.url().clone(), creds),
+                None => warn!("not found credentials of {} for {}", id, auth.url()),
+            }
+        }
+        let mut explicit = opt.mirror.clone();
+        for mirror in config.mirrors() {
+            explicit.push(mirror.parse::<Mirror>()?);
+        }
+        for mirror in &mut explicit {
+            if let Mirror::Url(url) = mirror {
+                if let Some(creds) = take_userinfo(url) {
+                    auths.push(url.clone(), creds);
+                }
+            }
+        }
+        let mut settings_mirrors = vec![];
+        if maven_settings {
+            if http_config.proxy().is_none() {
+                if let Some(proxy) = settings.active_proxy() {
+                    http_config.set_proxy(Some(proxy.url()?));
+                    http_config.set_no_proxy(proxy.no_proxy());
+                }
+            }
+            for mirror in settings.mirrors() {
+                let mut url = match parse_mirror(mirror.url()) {
+                    Ok(url) => url,
+                    Err(e) => {
+                        warn!("ignored mirror {} in maven settings: {}", mirror.id(), e);
+                        continue;
+                    }
+                };
+                let server = settings.server(mirror.id());
+                if let Some(creds) = take_userinfo(&mut url) {
+                    auths.push(url.clone(), creds);
+                } else if let Some(username) = server.and_then(|s| s.username().clone()) {
+                    if auths.find(&url).is_none() {
+                        let password = server.and_then(|s| s.password().clone());
+                        auths.push(url.clone(), Credentials::Basic { username, password });
+                    }
+                }
+                settings_mirrors.push((Mirror::Url(url), mirror.layout(opt.layout)));
+            }
+        }
+        let mirrors = merge_mirrors(explicit, opt.layout, settings_mirrors)?;
+        debug!("using mirrors: {:?}, auths: {:?}", mirrors, auths);
+        http::init(&http_config, auths)?;
+        let sources = Mirrors::new(
+            mirrors
+                .iter()
+                .map(|(mirror, layout)| layout.source(mirror.clone()))
+                .collect::<Result<Vec<_>>>()?,
+        )?;
         Ok(Self {
-            manager: Manager::new(Site::new(opt.mirror.clone()).expect("new site error"))?,
+            config,
+            config_path,
+            mirrors,
+            manager: Manager::new(sources, verify_keys, channel)?,
             opt,
-            base_dir,
-            project_dirs: ProjectDirs::from("xyz", "navyd", CRATE_NAME)
-                .ok_or_else(|| anyhow!("project dir error"))?,
+            installs: Installs::new(project_dirs.data_dir()),
+            link: base_dir
+                .executable_dir()
+                .map(|p| BinLink::new(p.join("mvn"))),
+            shims: base_dir.executable_dir().map(Shims::new),
+            pin: OnceCell::new(),
         })
     }
 
     pub async fn run(&self) {
         match &self.opt.commands {
-            Some(Commands::List { limit }) => {
-                if let Err(e) = self.list(*limit).await {
+            Some(Commands::List { limit, installed }) => {
+                let res = if *installed {
+                    self.list_installed()
+                } else {
+                    self.list(*limit).await
+                };
+                if let Err(e) = res {
                     eprintln!("list failed: {}", e);
                     exit(1);
                 }
             }
-            Some(Commands::Install { version }) => {
-                if let Err(e) = self.install(version.as_deref()).await {
+            Some(Commands::Install {
+                version,
+                from,
+                sha512,
+                no_verify,
+            }) => {
+                let res = match from {
+                    Some(from) => self.install_from(from, sha512.as_deref(), *no_verify).await,
+                    None => self.install(version.as_deref()).await,
+                };
+                if let Err(e) = res {
                     eprintln!("install failed: {}", e);
                     exit(1);
                 }
             }
-            Some(Commands::Uninstall) => {
-                if let Err(e) = self.uninstall().await {
+            Some(Commands::Uninstall { version }) => {
+                if let Err(e) = self.uninstall(version.as_deref()).await {
                     eprintln!("uninstall failed: {}", e);
                     exit(1);
                 }
@@ -120,6 +361,46 @@ impl Program {
                     exit(1);
                 }
             }
+            Some(Commands::Shim { disable }) => {
+                if let Err(e) = self.shim(*disable) {
+                    eprintln!("shim failed: {}", e);
+                    exit(1);
+                }
+            }
+            Some(Commands::Exec { command, args }) => {
+                if let Err(e) = self.exec(command, args) {
+                    eprintln!("exec failed: {}", e);
+                    exit(1);
+                }
+            }
+            Some(Commands::Current) => {
+                if let Err(e) = self.current() {
+                    eprintln!("current failed: {}", e);
+                    exit(1);
+                }
+            }
+            Some(Commands::Rollback) => {
+                if let Err(e) = self.rollback() {
+                    eprintln!("rollback failed: {}", e);
+                    exit(1);
+                }
+            }
+            Some(Commands::Mirrors(MirrorsCommand::Bench {
+                discover,
+                bytes,
+                save,
+            })) => {
+                if let Err(e) = self.bench_mirrors(*discover, *bytes, *save).await {
+                    eprintln!("mirrors bench failed: {}", e);
+                    exit(1);
+                }
+            }
+            Some(Commands::Use { version }) => {
+                if let Err(e) = self.use_version(version) {
+                    eprintln!("use failed: {}", e);
+                    exit(1);
+                }
+            }
             None => {
                 if let Err(e) = self.check().await {
                     eprintln!("check failed: {}", e);
@@ -129,29 +410,149 @@ impl Program {
         }
     }
 
-    async fn update(&self, version: Option<&str>) -> Result<()> {
-        let bin_link_path = which("mvn")?;
-        if !bin_link_path.symlink_metadata()?.file_type().is_symlink() {
-            bail!(
-                "not found mvn installed path for bin: {}",
-                bin_link_path.display()
-            );
+    /// 当前使用的mvnup安装的版本
+    fn active_version(&self) -> Option<Version> {
+        self.installs
+            .global()
+            .filter(|ver| self.installs.is_installed(ver))
+            .or_else(|| {
+                self.link
+                    .as_ref()
+                    .and_then(|link| link.active(&self.installs))
+            })
+    }
+
+    fn shims_enabled(&self) -> bool {
+        self.shims.as_ref().map(Shims::is_enabled).unwrap_or(false)
+    }
+
+    fn shim(&self, disable: bool) -> Result<()> {
+        let (shims, link) = self
+            .shims
+            .as_ref()
+            .zip(self.link.as_ref())
+            .ok_or_else(|| anyhow!("not found executable dir for shims"))?;
+        let active = self.active_version();
+        if disable {
+            shims.disable()?;
+            if let Some(ver) = active {
+                // 移除shim后仍存在的文件不是由mvnup管理
+                if link.is_foreign(&self.installs) {
+                    bail!(
+                        "{} is not a link managed by {}",
+                        link.path().display(),
+                        CRATE_NAME
+                    );
+                }
+                link.point_to(&self.installs.exe(&ver))?;
+                self.installs
+                    .activate(Some(&ver), &[link.path().to_path_buf()])?;
+                println!("linked {} to maven {}", link.path().display(), ver);
+            }
+            return Ok(());
+        }
+
+        shims.enable(&std::env::current_exe()?, &self.installs)?;
+        // 切换前链接指向的版本作为全局默认版本
+        if let Some(ver) = active {
+            self.installs.activate(Some(&ver), &[])?;
+        }
+        println!(
+            "shims enabled in {}. mvn now follows {} files",
+            link.path()
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            PIN_FILENAME
+        );
+        Ok(())
+    }
+
+    /// 使用当前目录选择的版本执行maven命令
+    fn exec(&self, command: &str, args: &[OsString]) -> Result<()> {
+        if Path::new(command).file_name() != Some(command.as_ref()) {
+            bail!("invalid command: {}", command);
+        }
+        let ver = match self.pin() {
+            Some(pin) => self.pinned_version(pin)?,
+            None => self.active_version().ok_or_else(|| {
+                anyhow!(
+                    "not found maven version for current dir. just type: {} use <version>",
+                    CRATE_NAME
+                )
+            })?,
+        };
+        let exe = self.installs.home(&ver).join("bin").join(command);
+        if !exe.is_file() {
+            bail!("not found {} in maven {}", command, ver);
+        }
+        debug!("executing {} with args {:?}", exe.display(), args);
+        let mut cmd = std::process::Command::new(&exe);
+        cmd.args(args);
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            let e = cmd.exec();
+            bail!("failed to exec {}: {}", exe.display(), e)
+        }
+        #[cfg(not(unix))]
+        {
+            let status = cmd.status()?;
+            exit(status.code().unwrap_or(1));
+        }
+    }
+
+    /// 从当前目录向上查找的pin文件。无效的pin文件只提示并忽略，不影响其它命令
+    fn pin(&self) -> Option<&Pin> {
+        self.pin
+            .get_or_init(|| {
+                let found = std::env::current_dir()
+                    .map_err(Into::into)
+                    .and_then(Pin::find);
+                found.unwrap_or_else(|e| {
+                    eprintln!("warning: ignored pin file: {}", e);
+                    None
+                })
+            })
+            .as_ref()
+    }
+
+    /// 当前目录pin文件固定的已安装版本
+    fn pinned_version(&self, pin: &Pin) -> Result<Version> {
+        self.installs.find(pin.version()).map_err(|_| {
+            anyhow!(
+                "pinned version {} is not installed. just type: {} install",
+                pin,
+                CRATE_NAME
+            )
+        })
+    }
+
+    fn current(&self) -> Result<()> {
+        if let Some(pin) = self.pin() {
+            let ver = self.pinned_version(pin)?;
+            println!("{} (set by {})", ver, pin.path().display());
+        } else {
+            let ver = self
+                .active_version()
+                .ok_or_else(|| anyhow!("not found active maven installed by {}", CRATE_NAME))?;
+            println!("{} (global)", ver);
         }
-        let bin_path = bin_link_path.read_link()?;
+        Ok(())
+    }
 
-        let installed_ver = find_mvn_version(&bin_path)?;
+    async fn update(&self, version: Option<&str>) -> Result<()> {
+        let installed_ver = self
+            .active_version()
+            .ok_or_else(|| anyhow!("not found active maven installed by {}", CRATE_NAME))?;
         let ver = if let Some(ver_pat) = version {
             self.manager.match_version(ver_pat).await?
         } else {
             self.manager.latest_version().await?
         };
 
-        let mvn_path = match installed_ver.cmp(&ver) {
-            std::cmp::Ordering::Less => bin_path
-                .parent()
-                .and_then(|p| p.parent())
-                .and_then(|p| p.parent())
-                .ok_or_else(|| anyhow!("not found 3 parents in {}", bin_path.display()))?,
+        match installed_ver.cmp(&ver) {
+            std::cmp::Ordering::Less => {}
             std::cmp::Ordering::Equal => {
                 bail!("same version: {}", ver);
             }
@@ -159,122 +560,315 @@ impl Program {
                 bail!("less version: {}", ver);
             }
         };
-        println!("found mvn path: {}", mvn_path.display());
-        self.uninstall().await?;
-        self.install(Some(&ver.to_string())).await?;
+
+        // 失败时恢复安装记录与链接
+        let registry = self.installs.registry()?;
+        let link_target = self.link.as_ref().and_then(BinLink::target);
+        let fresh = !self.installs.is_installed(&ver);
+        let res = async {
+            if fresh {
+                let home = self.fetch(&ver).await?;
+                self.installs.mark_updated(&ver)?;
+                println!("installed maven {} in {}", ver, home.display());
+            }
+            self.use_version(&format!("={}", ver))?;
+            let pinned = self.pin().and_then(|pin| pin.req().ok());
+            self.installs.retain_previous(
+                &installed_ver,
+                *self.config.keep_versions(),
+                pinned.as_ref(),
+            )
+        }
+        .await;
+        let outdated = match res {
+            Ok(outdated) => outdated,
+            Err(e) => {
+                warn!("failed to update to {}, restoring {}", ver, installed_ver);
+                if let Err(e) = self.restore(registry, link_target, fresh.then_some(&ver)) {
+                    error!("failed to restore maven {}: {}", installed_ver, e);
+                }
+                return Err(e);
+            }
+        };
+
+        // 已切换到新版本，删除旧版本失败不影响更新
+        for old in outdated {
+            println!(
+                "removing old mvn home {}",
+                self.installs.home(&old).display()
+            );
+            if let Err(e) = self.installs.remove(&old) {
+                warn!("failed to remove old version {}: {}", old, e);
+            }
+        }
+        if self.installs.is_installed(&installed_ver) {
+            println!(
+                "kept {} for rollback. switch back by: {} rollback",
+                installed_ver, CRATE_NAME
+            );
+        }
         Ok(())
     }
 
-    async fn uninstall(&self) -> Result<()> {
-        let bin_link_path = which("mvn").map_err(|e| anyhow!("not found maven path: {}", e))?;
-        if let Some(exe_path) = self.base_dir.executable_dir().map(|p| p.join("mvn")) {
-            if exe_path != bin_link_path {
-                bail!(
-                    "inconsistent bin path: {}, original path: {}",
-                    bin_link_path.display(),
-                    exe_path.display()
-                );
+    /// 不访问网络切换回update前使用的版本
+    fn rollback(&self) -> Result<()> {
+        let ver = self
+            .installs
+            .previous()?
+            .ok_or_else(|| anyhow!("not found previous version to roll back"))?;
+        let active = self.active_version();
+        self.use_version(&format!("={}", ver))?;
+        self.installs.forget_previous(&ver)?;
+        if let Some(active) = active {
+            println!(
+                "rolled back from {} to {}. {} is still installed",
+                active, ver, active
+            );
+        }
+        Ok(())
+    }
+
+    /// 恢复更新前的安装记录与链接，并删除新安装的版本`fresh`
+    fn restore(
+        &self,
+        registry: Registry,
+        link_target: Option<PathBuf>,
+        fresh: Option<&Version>,
+    ) -> Result<()> {
+        if let Some(link) = &self.link {
+            match link_target {
+                Some(target) => link.point_to(&target)?,
+                None if link.active(&self.installs).is_some() => link.remove()?,
+                None => {}
             }
         }
+        if let Some(ver) = fresh {
+            let home = self.installs.home(ver);
+            if home.exists() {
+                debug!("removing new mvn home {}", home.display());
+                std::fs::remove_dir_all(&home)?;
+            }
+        }
+        self.installs.save(registry)
+    }
 
-        let bin_path = bin_link_path
-            .read_link()
-            .map_err(|e| anyhow!("{} is not a link: {}", bin_link_path.display(), e))?;
+    async fn uninstall(&self, version: Option<&str>) -> Result<()> {
+        let active = self.active_version();
+        let ver = match version {
+            Some(ver_pat) => self.installs.find(ver_pat)?,
+            None => active
+                .clone()
+                .ok_or_else(|| anyhow!("not found active maven installed by {}", CRATE_NAME))?,
+        };
 
-        let ver = find_mvn_version(&bin_path)?;
+        // 链接在删除记录前确定所属版本
+        let linked = self
+            .link
+            .as_ref()
+            .filter(|link| link.active(&self.installs).as_ref() == Some(&ver));
+        let (record, mut removed_links) = self.installs.remove(&ver)?;
+        println!("removed a mvn home {}", record.home.display());
+        if let Some(link) = linked.filter(|l| l.path().symlink_metadata().is_ok()) {
+            link.remove()?;
+            removed_links.push(link.path().to_path_buf());
+        }
+        for link in &removed_links {
+            println!("removed a mvn link {}", link.display());
+        }
+        if active.as_ref() == Some(&ver) {
+            self.installs.activate(None, &[])?;
+        }
+        Ok(())
+    }
 
-        let bin_data_paths = glob(&format!(
-            "{}/*{}*/**/mvn",
-            self.project_dirs.data_dir().display(),
-            ver,
-        ))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err::<Error, _>(Into::into)?;
-        debug!("found bin exe paths {:?}", bin_data_paths);
-        if bin_data_paths.is_empty() {
+    async fn install(&self, version: Option<&str>) -> Result<()> {
+        self.check_data_dir().await?;
+
+        // match mvn version
+        let mvn_version = if let Some(ver_pat) = version {
+            self.manager.match_version(ver_pat).await?
+        } else if let Some(pin) = self.pin() {
+            println!("using pinned version {}", pin);
+            self.manager.match_version(pin.version()).await?
+        } else {
+            self.manager.latest_version().await?
+        };
+        if self.installs.is_installed(&mvn_version) {
             bail!(
-                "not found mvn bin in data dir: {}",
-                self.project_dirs.data_dir().display()
+                "version {} already installed in {}",
+                mvn_version,
+                self.installs.home(&mvn_version).display()
             );
-        } else if bin_data_paths.len() >= 2 {
-            bail!("found multiple bin paths: {:?}", bin_data_paths);
-        }
-
-        // remove mvn home
-        let installed_path = &bin_data_paths[0];
-        let mvn_home = installed_path
-            .parent()
-            .and_then(|p| p.parent())
-            .ok_or_else(|| anyhow!("not found 2 parents dir for {}", installed_path.display()))?;
-        println!("removing a mvn home {}", mvn_home.display());
-        remove_dir_all(mvn_home)?;
-
-        // remove link
-        println!("removing a mvn link {}", bin_link_path.display());
-        remove_file(&bin_link_path)?;
-        Ok(())
+        }
+        let home = self.fetch(&mvn_version).await?;
+        println!("installed maven {} in {}", mvn_version, home.display());
+        self.link_installed(&mvn_version)
     }
 
-    async fn install(&self, version: Option<&str>) -> Result<()> {
-        if let Ok(p) = which("mvn") {
+    /// 从本地压缩文件或任意url安装。版本取自文件名，无法识别时运行解压出的`bin/mvn`。
+    /// 总是校验`sha512`或摘要文件，`no_verify`时跳过
+    async fn install_from(&self, from: &str, sha512: Option<&str>, no_verify: bool) -> Result<()> {
+        self.check_data_dir().await?;
+        let url = parse_location(from)?;
+        let filename = get_filename(&url)?;
+        if ArchiveFormat::from_filename(&filename).is_none() {
+            bail!("unsupported archive: {}", filename);
+        }
+        let ver = parse_archive_version(&filename);
+        if let Some(ver) = ver.as_ref().filter(|ver| self.installs.is_installed(ver)) {
             bail!(
-                "found installed version {} in {}",
-                find_mvn_version(&p)?,
-                p.display()
+                "version {} already installed in {}",
+                ver,
+                self.installs.home(ver).display()
             );
         }
 
-        let install_path = self.project_dirs.data_dir();
-        // check path
+        let digest = match sha512 {
+            Some(value) => Some(parse_digest("Sha512", value)?),
+            None if no_verify => {
+                println!("skipped verifying digest of {}", filename);
+                None
+            }
+            None => Some(fetch_digest(&url).await?.ok_or_else(|| {
+                anyhow!(
+                    "not found digest for {}. verify it by: --sha512 <digest>, or skip by: --no-verify",
+                    filename
+                )
+            })?),
+        };
+        let signature = url.join(&format!("{}.asc", filename))?;
+        let bin = BinFile::fetch(url, digest, Some(signature)).await?;
+        let down_path = self.manager.download_bin(&bin).await?;
+
+        let (mvn_version, home) = match ver {
+            Some(ver) => {
+                let home = self.installs.install(&down_path, &ver)?;
+                (ver, home)
+            }
+            None => self.installs.install_detected(&down_path)?,
+        };
+        self.installs.register(InstallRecord {
+            version: mvn_version.clone(),
+            url: Some(bin.url().clone()),
+            filename: Some(bin.filename().clone()),
+            digest: bin.digest().clone(),
+            home: home.clone(),
+            links: vec![],
+            installed_at: Local::now(),
+        })?;
+        println!("installed maven {} in {}", mvn_version, home.display());
+        self.link_installed(&mvn_version)
+    }
+
+    /// 检查安装使用的数据目录，不存在时创建
+    async fn check_data_dir(&self) -> Result<()> {
+        let install_path = self.installs.data_dir();
         if !install_path.exists() {
             info!("creating dir {} for installation", install_path.display());
             afs::create_dir_all(install_path).await?;
         } else if !install_path.is_dir() {
             bail!("{} is not a dir", install_path.display());
         }
+        Ok(())
+    }
 
-        // match mvn version
-        let mvn_version = if let Some(ver_pat) = version {
-            self.manager.match_version(ver_pat).await?
-        } else {
-            self.manager.latest_version().await?
-        };
-        // download
-        let down_path = self.manager.download(&mvn_version).await?;
-        // extract to path
-        extract(down_path.as_path(), install_path)?;
-
-        // link to $PATH
-        let exe_path = glob(&format!("{}/**/mvn", install_path.display()))
-            .map_err::<Error, _>(Into::into)?
-            .flatten()
-            .next()
-            .ok_or_else(|| anyhow!("not found mvn bin in {}", install_path.display()))?
-            .canonicalize()?;
-        #[cfg(target_os = "linux")]
-        {
-            if let Some(bin_path) = self.base_dir.executable_dir().map(|p| p.join("mvn")) {
-                if !bin_path.exists() {
-                    println!(
-                        "creating link {} for {}",
-                        bin_path.display(),
-                        exe_path.display(),
-                    );
-                    std::os::unix::fs::symlink(exe_path, bin_path)?;
-                    println!("installation successful. just type: mvn --version");
-                    return Ok(());
-                }
+    /// 新安装的版本`mvn_version`没有正在使用的版本时链接到$PATH，否则提示切换
+    fn link_installed(&self, mvn_version: &Version) -> Result<()> {
+        let exe_path = self.installs.exe(mvn_version);
+        let has_active = self.active_version().is_some();
+        match &self.link {
+            Some(_) if !has_active && self.shims_enabled() => {
+                self.use_version(&format!("={}", mvn_version))?;
+                println!("installation successful. just type: mvn --version");
+            }
+            Some(link) if link.path().symlink_metadata().is_err() => {
+                println!(
+                    "creating link {} for {}",
+                    link.path().display(),
+                    exe_path.display(),
+                );
+                self.use_version(&format!("={}", mvn_version))?;
+                println!("installation successful. just type: mvn --version");
+            }
+            Some(link) if self.shims_enabled() || !link.is_foreign(&self.installs) => {
+                println!(
+                    "installation successful. switch to it by: {} use {}",
+                    CRATE_NAME, mvn_version
+                );
+            }
+            _ => {
+                println!(
+                    "installation successful. please add {} to your PATH",
+                    exe_path.display()
+                );
             }
         }
+        Ok(())
+    }
+
+    /// 下载、校验并解压版本`ver`，完成后记录安装
+    async fn fetch(&self, ver: &Version) -> Result<PathBuf> {
+        let (down_path, bin) = self.manager.download(ver).await?;
+        let home = self.installs.install(down_path.as_path(), ver)?;
+        self.installs.register(InstallRecord {
+            version: ver.clone(),
+            url: Some(bin.url().clone()),
+            filename: Some(bin.filename().clone()),
+            digest: bin.digest().clone(),
+            home: home.clone(),
+            links: vec![],
+            installed_at: Local::now(),
+        })?;
+        Ok(home)
+    }
+
+    /// 切换全局默认版本。未启用shim时原子地将链接切换到该版本
+    fn use_version(&self, ver_pat: &str) -> Result<()> {
+        let ver = self.installs.find(ver_pat)?;
+        let mut links = vec![];
+        if !self.shims_enabled() {
+            let link = self
+                .link
+                .as_ref()
+                .ok_or_else(|| anyhow!("not found executable dir for mvn link"))?;
+            if link.is_foreign(&self.installs) {
+                bail!(
+                    "{} is not a link managed by {}",
+                    link.path().display(),
+                    CRATE_NAME
+                );
+            }
+            link.point_to(&self.installs.exe(&ver))?;
+            links.push(link.path().to_path_buf());
+        }
+        self.installs.activate(Some(&ver), &links)?;
         println!(
-            "installation successful. please add {} to your PATH",
-            exe_path.display()
+            "using maven {} in {}",
+            ver,
+            self.installs.home(&ver).display()
         );
         Ok(())
     }
 
+    fn list_installed(&self) -> Result<()> {
+        let active = self.active_version();
+        let vers = self.installs.versions()?;
+        if vers.is_empty() {
+            println!("not found any installed version");
+        }
+        for ver in vers {
+            let mark = if active.as_ref() == Some(&ver) {
+                "*"
+            } else {
+                " "
+            };
+            println!("{} {}\t{}", mark, ver, self.installs.home(&ver).display());
+        }
+        Ok(())
+    }
+
     async fn list(&self, limit: usize) -> Result<()> {
-        let vers = self.manager.versions().await?;
+        let vers = self.manager.channel_versions().await?;
         let limit = if vers.len() < limit {
             vers.len()
         } else {
@@ -284,17 +878,35 @@ impl Program {
         let bins = self.manager.get_multi_bins(&vers[..limit]).await?;
 
         let mut table = Table::new();
-        table.set_header(vec!["version", "pulished date", "filename", "size: MB"]);
+        table.set_header(vec![
+            "version",
+            "java",
+            "pulished date",
+            "filename",
+            "size: MB",
+        ]);
         for (ver, files) in bins {
             for file in files {
-                let size = (*file.size() as f64 / (1024.0 * 1024.0))
-                    .to_string()
-                    .chars()
-                    .take(6)
-                    .collect::<String>();
+                let size = file
+                    .size()
+                    .map(|size| {
+                        (size as f64 / (1024.0 * 1024.0))
+                            .to_string()
+                            .chars()
+                            .take(6)
+                            .collect::<String>()
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+                let java = min_java_version(&ver)
+                    .map(|v| format!("{}+", v))
+                    .unwrap_or_default();
                 table.add_row(vec![
                     &ver.to_string(),
-                    &file.last_modified().to_string(),
+                    &java,
+                    &file
+                        .last_modified()
+                        .map(|d| d.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
                     file.filename(),
                     &size,
                 ]);
@@ -309,28 +921,134 @@ impl Program {
         Ok(())
     }
 
-    async fn check(&self) -> Result<()> {
-        let p = which("mvn")?;
+    /// 测速命令行与配置中的镜像，`auto`或`discover`时包含apache镜像选择器发现的镜像
+    async fn bench_mirrors(&self, discover: bool, bytes: u64, save: bool) -> Result<()> {
+        if self.opt.layout != Layout::Dist {
+            bail!("mirrors bench only supports the dist layout");
+        }
+        let mut urls = vec![];
+        let mut discover = discover;
+        for (mirror, layout) in &self.mirrors {
+            if *layout != Layout::Dist {
+                debug!("skipped {} mirror {}", layout.as_ref(), mirror);
+                continue;
+            }
+            match mirror {
+                Mirror::Url(url) => urls.push(url.clone()),
+                Mirror::Auto => {
+                    discover = true;
+                    urls.push(ARCHIVE_URL.parse()?);
+                }
+            }
+        }
+        if discover {
+            match discover_mirrors().await {
+                Ok(found) => urls.extend(found),
+                Err(e) => warn!("failed to discover mirrors: {}", e),
+            }
+        }
+        let mut seen = vec![];
+        urls.retain(|url| {
+            let new = !seen.contains(url);
+            seen.push(url.clone());
+            new
+        });
+
+        let mut results = vec![];
+        for url in urls {
+            println!("benchmarking {}", url);
+            results.push(bench(&Site::new(url)?, bytes).await);
+        }
+        rank(&mut results);
+
+        let mut table = Table::new();
+        table.set_header(vec![
+            "rank",
+            "mirror",
+            "latency: ms",
+            "speed: MB/s",
+            "error",
+        ]);
+        for (i, res) in results.iter().enumerate() {
+            let latency = res
+                .latency()
+                .map(|d| d.as_millis().to_string())
+                .unwrap_or_default();
+            let speed = res
+                .throughput()
+                .map(|t| format!("{:.2}", t / (1024.0 * 1024.0)))
+                .unwrap_or_default();
+            table.add_row(vec![
+                &(i + 1).to_string(),
+                res.mirror().as_str(),
+                &latency,
+                &speed,
+                res.error().as_deref().unwrap_or_default(),
+            ]);
+        }
+        println!("{}", table);
+
+        let winner = results
+            .iter()
+            .find(|res| res.is_ok())
+            .ok_or_else(|| anyhow!("all mirrors failed"))?;
+        if save {
+            Config::prefer_mirror(&self.config_path, winner.mirror().as_str())?;
+            println!(
+                "saved mirror {} to {}",
+                winner.mirror(),
+                self.config_path.display()
+            );
+        } else {
+            println!(
+                "fastest mirror: {}. save it by: {} mirrors bench --save",
+                winner.mirror(),
+                CRATE_NAME
+            );
+        }
+        Ok(())
+    }
 
-        debug!("found mvn path: {}", p.display());
-        let cur_ver = find_mvn_version(p.clone())?;
+    async fn check(&self) -> Result<()> {
+        let (cur_ver, p) = match self.pin() {
+            Some(pin) => {
+                let ver = self.pinned_version(pin)?;
+                println!("found pinned version {}", pin);
+                (ver.clone(), self.installs.exe(&ver))
+            }
+            None => match self.active_version() {
+                Some(ver) => (ver.clone(), self.installs.exe(&ver)),
+                // 检查不是由mvnup安装的mvn
+                None => {
+                    let p = which("mvn")?;
+                    debug!("found mvn path: {}", p.display());
+                    (find_mvn_version(p.clone())?, p)
+                }
+            },
+        };
         println!(
             "found installed maven version: {}, path: {}",
             cur_ver,
             p.display()
         );
-        let latest_ver = self.manager.latest_version().await?;
+        // pin文件固定的版本只在其范围内检查更新
+        let latest_ver = match self.pin() {
+            Some(pin) => self.manager.match_version(pin.version()).await?,
+            None => self.manager.latest_version().await?,
+        };
 
+        // 服务器没有返回修改时间时显示为`-`
+        let bins_date = |bins: &[BinFile]| {
+            bins.first()
+                .and_then(|bin| *bin.last_modified())
+                .map(|d| d.date_naive().to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
         let (cur_date, latest_date) = try_join!(
-            self.manager.site.fetch_bins(cur_ver.clone()),
-            self.manager.site.fetch_bins(latest_ver.clone())
+            self.manager.source.fetch_bins(&cur_ver),
+            self.manager.source.fetch_bins(&latest_ver)
         )
-        .map(|(cur_bins, latest_bins)| {
-            (
-                cur_bins[0].last_modified().date().to_string(),
-                latest_bins[0].last_modified().date().to_string(),
-            )
-        })?;
+        .map(|(cur_bins, latest_bins)| (bins_date(&cur_bins), bins_date(&latest_bins)))?;
 
         use std::cmp::Ordering::*;
         match cur_ver.cmp(&latest_ver) {
@@ -355,69 +1073,190 @@ impl Program {
     }
 }
 
+/// 签名校验使用的公钥来源
+enum KeysSource {
+    Mirror,
+    Local(PathBuf),
+}
+
 struct Manager {
-    site: Site,
+    source: Mirrors,
     cache_dir: PathBuf,
     versions: Arc<Mutex<Vec<Version>>>,
+    verify_keys: Option<KeysSource>,
+    channel: Channel,
+    java_version: OnceCell<Option<JavaVersion>>,
 }
 
 impl Manager {
-    pub fn new(site: Site) -> Result<Self> {
+    pub fn new(source: Mirrors, verify_keys: Option<KeysSource>, channel: Channel) -> Result<Self> {
         let project_dirs = ProjectDirs::from("xyz", "navyd", CRATE_NAME)
             .ok_or_else(|| anyhow!("project dir error"))?;
         let cache_dir = project_dirs.cache_dir().to_path_buf();
         std::fs::create_dir_all(&cache_dir)?;
         Ok(Self {
             versions: Arc::new(Mutex::new(vec![])),
-            site,
+            source,
             cache_dir,
+            verify_keys,
+            channel,
+            java_version: OnceCell::new(),
         })
     }
 
     fn choose_bin<'a>(&self, bins: &'a [BinFile]) -> Result<&'a BinFile> {
-        let tar_suffix = [".tar.gz", ".tar.bz2", ".tar.xz"]
-            .into_iter()
-            .collect::<HashSet<_>>();
-        let has_tar = which("tar").is_ok();
-        for bin in bins {
-            if has_tar && tar_suffix.iter().any(|s| bin.filename().ends_with(s)) {
-                return Ok(bin);
-            }
-        }
-        bail!("not found a bin")
+        ArchiveFormat::ALL
+            .iter()
+            .find_map(|format| {
+                bins.iter()
+                    .find(|bin| ArchiveFormat::from_filename(bin.filename()) == Some(*format))
+            })
+            .ok_or_else(|| anyhow!("not found a supported bin in {} files", bins.len()))
     }
 
-    async fn download(&self, ver: &Version) -> Result<PathBuf> {
-        let bins = self.site.fetch_bins(ver.clone()).await?;
-        let select_bin = self.choose_bin(&bins)?;
+    /// 下载版本`ver`。下载或校验失败时尝试下一个镜像
+    async fn download(&self, ver: &Version) -> Result<(PathBuf, BinFile)> {
+        self.source
+            .try_each(&format!("download {}", ver), |source| {
+                Box::pin(async move {
+                    let bins = source.fetch_bins(ver).await?;
+                    let select_bin = self.choose_bin(&bins)?;
+                    let down_path = self.download_bin(select_bin).await?;
+                    Ok((down_path, select_bin.clone()))
+                })
+            })
+            .await
+    }
 
-        let down_path = self.cache_dir.join(select_bin.filename());
-        if down_path.is_file() && match_digests(down_path.as_path(), select_bin) {
+    /// 下载`bin`到缓存目录并校验。缓存的文件一致时不再下载
+    async fn download_bin(&self, bin: &BinFile) -> Result<PathBuf> {
+        let down_path = self.cache_dir.join(bin.filename());
+        if down_path.is_file() && match_digests(down_path.as_path(), bin)? {
             // cache
             println!("using cached file: {}", down_path.display());
         } else {
-            println!("downloading {} of version: {}", select_bin.filename(), ver);
-            select_bin.download(down_path.as_path()).await?;
+            println!("downloading {} from {}", bin.filename(), bin.url());
+            bin.download(down_path.as_path()).await?;
+        }
+        if let Some(keys) = &self.verify_keys {
+            match self.verify_signature(bin, &down_path, keys).await {
+                Ok(fingerprint) => {
+                    println!(
+                        "good signature for {} from key: {}",
+                        bin.filename(),
+                        fingerprint
+                    );
+                }
+                Err(e) => {
+                    remove_file(&down_path)?;
+                    return Err(e);
+                }
+            }
         }
         Ok(down_path)
     }
 
-    async fn match_version(&self, ver_pat: &str) -> Result<Version> {
-        // check java version
-        trace!("finding java version");
-        let _java_ver = which("java")
-            .map_err(Into::into)
-            .and_then(find_java_version)
-            .map_err(|e| anyhow!("failed to find java version: {}", e))?;
-        // todo: match with java version
+    /// 校验文件签名并返回签名者指纹
+    async fn verify_signature(
+        &self,
+        bin: &BinFile,
+        path: &Path,
+        keys: &KeysSource,
+    ) -> Result<String> {
+        let keyring = match keys {
+            KeysSource::Local(p) => Keyring::load(p)?,
+            KeysSource::Mirror => {
+                trace!("fetching KEYS from mirror {}", self.source.url());
+                Keyring::parse(&self.source.fetch_keys().await?)?
+            }
+        };
+        let signature = bin.fetch_signature().await?;
+        keyring
+            .verify(path, &signature)
+            .map_err(|e| anyhow!("failed to verify signature: {}", e))
+    }
+
+    /// 当前使用的java版本。未找到java时不按java版本过滤maven版本
+    fn java_version(&self) -> Option<&JavaVersion> {
+        self.java_version
+            .get_or_init(|| {
+                trace!("finding java version");
+                match find_java().and_then(find_java_version) {
+                    Ok(ver) => {
+                        debug!("found java version {}", ver);
+                        Some(ver)
+                    }
+                    Err(e) => {
+                        warn!("failed to find java version: {}", e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
 
+    /// 从降序的`vers`中选出当前java可以运行的最新版本，并提示跳过的新版本
+    fn select_version<'a>(&self, vers: impl IntoIterator<Item = &'a Version>) -> Option<Version> {
+        let java = self.java_version();
+        let mut skipped = None;
+        for ver in vers {
+            match java {
+                Some(java) if !supports_java(ver, *java.feature()) => {
+                    skipped.get_or_insert(ver);
+                }
+                _ => {
+                    if let Some((skipped, java)) = skipped.zip(java) {
+                        println!(
+                            "skipped maven {} and newer: requires java {} but found java {}. using {}",
+                            skipped,
+                            min_java_version(skipped).unwrap_or_default(),
+                            java,
+                            ver
+                        );
+                    }
+                    return Some(ver.clone());
+                }
+            }
+        }
+        if let Some((skipped, java)) = skipped.zip(java) {
+            println!(
+                "maven {} requires java {} but found java {}",
+                skipped,
+                min_java_version(skipped).unwrap_or_default(),
+                java
+            );
+        }
+        None
+    }
+
+    async fn match_version(&self, ver_pat: &str) -> Result<Version> {
         let req = ver_pat.parse::<VersionReq>()?;
-        self.versions()
+        let vers = self.versions().await?;
+        let matched = match_versions(&req, &vers, self.channel);
+        if matched.is_empty() {
+            if let Some(pre) = vers.iter().find(|ver| req.matches(ver)) {
+                bail!(
+                    "{} only matches versions out of {} channel such as {}. include them by: --channel {}",
+                    ver_pat,
+                    self.channel,
+                    pre,
+                    pre.channel()
+                );
+            }
+            bail!("not matched version for {}", ver_pat);
+        }
+        self.select_version(matched)
+            .ok_or_else(|| anyhow!("not found version for {} running on current java", ver_pat))
+    }
+
+    /// 当前渠道中的版本
+    async fn channel_versions(&self) -> Result<Vec<Version>> {
+        Ok(self
+            .versions()
             .await?
-            .iter()
-            .find(|ver| req.matches(ver))
-            .cloned()
-            .ok_or_else(|| anyhow!("not matched version for {}", ver_pat))
+            .into_iter()
+            .filter(|ver| self.channel.allows(ver))
+            .collect())
     }
 
     async fn versions(&self) -> Result<Vec<Version>> {
@@ -425,15 +1264,19 @@ impl Manager {
         if !vers.is_empty() {
             return Ok(vers.to_vec());
         }
-        *vers = self.site.fetch_versions().await?;
+        *vers = self.source.fetch_versions().await?;
         vers.sort_unstable_by(|a, b| b.cmp(a));
         Ok(vers.to_vec())
     }
 
     async fn latest_version(&self) -> Result<Version> {
-        self.versions()
-            .await
-            .and_then(|vers| vers.first().cloned().ok_or_else(|| anyhow!("")))
+        let vers = self.channel_versions().await?;
+        self.select_version(&vers).ok_or_else(|| {
+            anyhow!(
+                "not found a version of {} channel running on current java",
+                self.channel
+            )
+        })
     }
 
     async fn get_multi_bins(&self, versions: &[Version]) -> Result<Vec<(Version, Vec<BinFile>)>> {
@@ -442,8 +1285,8 @@ impl Manager {
             let ver = ver.clone();
             async move {
                 let ver_str = ver.to_string();
-                self.site
-                    .fetch_bins(ver.clone())
+                self.source
+                    .fetch_bins(&ver)
                     .await
                     .map(|bins| (ver, bins))
                     .map_err(|e| {
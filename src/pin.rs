@@ -0,0 +1,100 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use getset::Getters;
+use log::{debug, trace};
+
+use crate::version::VersionReq;
+
+pub static PIN_FILENAME: &str = ".mvnup-version";
+
+/// 项目目录中`.mvnup-version`文件固定的maven版本
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct Pin {
+    /// pin文件路径
+    path: PathBuf,
+    /// pin文件中的版本要求
+    version: String,
+}
+
+impl Pin {
+    /// 从`dir`开始向上查找最近的pin文件
+    pub fn find(dir: impl AsRef<Path>) -> Result<Option<Self>> {
+        for dir in dir.as_ref().ancestors() {
+            let path = dir.join(PIN_FILENAME);
+            trace!("finding pin file {}", path.display());
+            if path.is_file() {
+                debug!("found pin file {}", path.display());
+                return Self::load(path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// 读取pin文件中第一个非空且不是`#`注释的行
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("failed to read pin file {}: {}", path.display(), e))?;
+        let version = content
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'))
+            .ok_or_else(|| anyhow!("not found version in pin file {}", path.display()))?
+            .to_string();
+        let pin = Self { path, version };
+        pin.req()
+            .map_err(|e| anyhow!("invalid version in {}: {}", pin.path.display(), e))?;
+        Ok(pin)
+    }
+
+    /// 版本要求。只有数字的版本如`3.8`匹配`3.8.x`，`3.8.4`只匹配`3.8.4`
+    pub fn req(&self) -> Result<VersionReq> {
+        self.version.parse()
+    }
+}
+
+impl Display for Pin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (set by {})", self.version, self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let dir = tempdir()?;
+        let sub = dir.path().join("a").join("b");
+        fs::create_dir_all(&sub)?;
+        assert_eq!(Pin::find(&sub)?, None);
+
+        let path = dir.path().join("a").join(PIN_FILENAME);
+        fs::write(&path, "# maven version\n\n3.8.4\n")?;
+        let pin = Pin::find(&sub)?.unwrap();
+        assert_eq!(pin.path(), &path);
+        assert_eq!(pin.version(), "3.8.4");
+        assert!(pin.req()?.matches(&"3.8.4".parse()?));
+        assert!(!pin.req()?.matches(&"3.8.5".parse()?));
+        assert!(!pin.req()?.matches(&"3.8.4.1".parse()?));
+        assert!(!pin.req()?.matches(&"3.8.4-rc-1".parse()?));
+
+        fs::write(&path, "3.8")?;
+        assert!(Pin::find(&sub)?.unwrap().req()?.matches(&"3.8.5".parse()?));
+
+        fs::write(&path, "~3.6")?;
+        assert!(Pin::find(&sub)?.unwrap().req()?.matches(&"3.6.3".parse()?));
+
+        fs::write(&path, "not a version")?;
+        assert!(Pin::find(&sub).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{site::Digest, version::Version};
+
+/// 一次安装的记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub version: Version,
+    /// 下载压缩文件的url
+    pub url: Option<Url>,
+    /// 压缩文件名
+    pub filename: Option<String>,
+    pub digest: Option<Digest>,
+    /// maven home
+    pub home: PathBuf,
+    /// 指向该版本的链接
+    #[serde(default)]
+    pub links: Vec<PathBuf>,
+    pub installed_at: DateTime<Local>,
+}
+
+impl InstallRecord {
+    pub fn exe(&self) -> PathBuf {
+        self.home.join("bin").join("mvn")
+    }
+}
+
+/// 保存在数据目录中的安装状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(skip)]
+    path: PathBuf,
+    /// 全局默认使用的版本
+    global: Option<Version>,
+    /// update前使用的版本，最近的在前
+    #[serde(default)]
+    previous: Vec<Version>,
+    /// 由update安装的版本，只有这些版本可能被update删除
+    #[serde(default)]
+    updated: Vec<Version>,
+    installs: Vec<InstallRecord>,
+}
+
+impl Registry {
+    /// 加载状态文件。文件不存在时返回空的状态
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            trace!("not found registry {}", path.display());
+            return Ok(Self {
+                path,
+                ..Default::default()
+            });
+        }
+        let content = fs::read_to_string(&path)?;
+        let mut registry = serde_json::from_str::<Self>(&content)
+            .map_err(|e| anyhow!("failed to parse registry {}: {}", path.display(), e))?;
+        registry.path = path;
+        Ok(registry)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// 先写入临时文件再重命名，避免中断时损坏状态文件
+    pub fn save(&self) -> Result<()> {
+        debug!("saving registry {}", self.path.display());
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, &self.path).map_err(Into::into)
+    }
+
+    pub fn get(&self, ver: &Version) -> Option<&InstallRecord> {
+        self.installs.iter().find(|r| &r.version == ver)
+    }
+
+    pub fn records(&self) -> &[InstallRecord] {
+        &self.installs
+    }
+
+    pub fn records_mut(&mut self) -> &mut [InstallRecord] {
+        &mut self.installs
+    }
+
+    /// 添加记录。已存在相同版本时替换
+    pub fn add(&mut self, record: InstallRecord) {
+        self.installs.retain(|r| r.version != record.version);
+        self.installs.push(record);
+        self.installs
+            .sort_unstable_by(|a, b| b.version.cmp(&a.version));
+    }
+
+    pub fn remove(&mut self, ver: &Version) -> Option<InstallRecord> {
+        let i = self.installs.iter().position(|r| &r.version == ver)?;
+        if self.global.as_ref() == Some(ver) {
+            self.global = None;
+        }
+        self.previous.retain(|v| v != ver);
+        self.updated.retain(|v| v != ver);
+        Some(self.installs.remove(i))
+    }
+
+    pub fn global(&self) -> Option<&Version> {
+        self.global.as_ref()
+    }
+
+    pub fn set_global(&mut self, ver: Option<Version>) {
+        self.global = ver;
+    }
+
+    pub fn previous(&self) -> &[Version] {
+        &self.previous
+    }
+
+    /// 记录update前使用的版本
+    pub fn push_previous(&mut self, ver: Version) {
+        self.previous.retain(|v| v != &ver);
+        self.previous.insert(0, ver);
+    }
+
+    pub fn remove_previous(&mut self, ver: &Version) {
+        self.previous.retain(|v| v != ver);
+    }
+
+    /// 记录由update安装的版本
+    pub fn mark_updated(&mut self, ver: Version) {
+        if !self.updated.contains(&ver) {
+            self.updated.push(ver);
+        }
+    }
+
+    pub fn is_updated(&self, ver: &Version) -> bool {
+        self.updated.contains(ver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(ver: &str) -> InstallRecord {
+        InstallRecord {
+            version: ver.parse().unwrap(),
+            url: Some(
+                format!(
+                    "https://archive.apache.org/dist/maven/maven-3/{0}/binaries/apache-maven-{0}-bin.tar.gz",
+                    ver
+                )
+                .parse()
+                .unwrap(),
+            ),
+            filename: Some(format!("apache-maven-{}-bin.tar.gz", ver)),
+            digest: Some(Digest::Sha512("abc".to_string())),
+            home: PathBuf::from("/data").join(ver),
+            links: vec![],
+            installed_at: Local::now(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("installs.json");
+        let mut registry = Registry::load(&path)?;
+        assert!(!registry.exists());
+
+        registry.add(record("3.6.3"));
+        registry.add(record("3.8.4"));
+        registry.set_global(Some("3.8.4".parse()?));
+        registry.push_previous("3.6.3".parse()?);
+        registry.mark_updated("3.8.4".parse()?);
+        registry.save()?;
+
+        let loaded = Registry::load(&path)?;
+        assert_eq!(loaded, registry);
+        assert_eq!(loaded.records()[0].version, "3.8.4".parse()?);
+
+        assert!(loaded.is_updated(&"3.8.4".parse()?));
+        registry.remove(&"3.8.4".parse()?);
+        assert_eq!(registry.global(), None);
+        assert!(!registry.is_updated(&"3.8.4".parse()?));
+        assert_eq!(registry.records().len(), 1);
+        registry.remove(&"3.6.3".parse()?);
+        assert!(registry.previous().is_empty());
+        Ok(())
+    }
+}
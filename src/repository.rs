@@ -0,0 +1,190 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail, Error, Result};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use getset::Getters;
+use log::{debug, trace, warn};
+use url::Url;
+
+use crate::{
+    archive::ArchiveFormat,
+    site::{fetch_cxt, fetch_digest, BinFile, Digest},
+    source::Source,
+    version::Version,
+};
+
+/// maven仓库中apache-maven的目录
+static ARTIFACT_PATH: &str = "org/apache/maven/apache-maven/";
+
+/// maven中央仓库只发布了这些格式的二进制文件
+static BIN_FORMATS: [ArchiveFormat; 2] = [ArchiveFormat::TarGz, ArchiveFormat::Zip];
+
+/// maven仓库目录结构的来源。如maven中央仓库及其Nexus、Artifactory代理：
+/// https://repo.maven.apache.org/maven2/
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
+pub struct Repository {
+    url: Url,
+}
+
+impl Repository {
+    pub fn new<U>(url: U) -> Result<Self>
+    where
+        U: TryInto<Url> + Display,
+        U::Error: Into<Error>,
+    {
+        let url = url.try_into().map_err(Into::into)?;
+        Ok(Self { url })
+    }
+
+    fn artifact_url(&self) -> Result<Url> {
+        self.url.join(ARTIFACT_PATH).map_err(Into::into)
+    }
+}
+
+#[async_trait]
+impl Source for Repository {
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// 从`maven-metadata.xml`中获取版本
+    async fn fetch_versions(&self) -> Result<Vec<Version>> {
+        let url = self.artifact_url()?.join("maven-metadata.xml")?;
+        debug!("fetching versions from {}", url);
+        let mut versions = parse_metadata(&fetch_cxt(url).await?)?;
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(versions)
+    }
+
+    async fn fetch_bins(&self, ver: &Version) -> Result<Vec<BinFile>> {
+        let dir = self.artifact_url()?.join(&format!("{}/", ver))?;
+        let tasks = BIN_FORMATS
+            .iter()
+            .map(|format| {
+                let name = format!("apache-maven-{}-bin{}", ver, format.suffix());
+                Ok((dir.join(&name)?, dir.join(&format!("{}.asc", name))?))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .map(|(url, signature)| async move {
+                trace!("fetching metadata and digest for {}", url);
+                let digest = self.fetch_digest(&url).await?;
+                BinFile::fetch(url.clone(), digest, Some(signature))
+                    .await
+                    .map_err(|e| anyhow!("failed to fetch {}: {}", url, e))
+            });
+        let mut bins = vec![];
+        for res in join_all(tasks).await {
+            match res {
+                Ok(bin) => bins.push(bin),
+                Err(e) => warn!("{}", e),
+            }
+        }
+        if bins.is_empty() {
+            bail!("not found any bins of {} in {}", ver, dir);
+        }
+        Ok(bins)
+    }
+
+    /// maven仓库总是发布摘要文件，不存在时不能校验下载的文件
+    async fn fetch_digest(&self, bin_url: &Url) -> Result<Option<Digest>> {
+        fetch_digest(bin_url)
+            .await?
+            .ok_or_else(|| anyhow!("not found any digest for {}", bin_url))
+            .map(Some)
+    }
+}
+
+/// 解析`maven-metadata.xml`中`<versioning><versions>`的版本
+fn parse_metadata(content: &str) -> Result<Vec<Version>> {
+    let doc = roxmltree::Document::parse(content)?;
+    let versions = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("versions"))
+        .flat_map(|node| node.children())
+        .filter(|node| node.has_tag_name("version"))
+        .filter_map(|node| node.text())
+        .flat_map(|s| s.parse::<Version>())
+        .collect::<Vec<_>>();
+    if versions.is_empty() {
+        bail!("not found any version in maven metadata");
+    }
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::site::digest_urls;
+
+    #[test]
+    fn test_parse_metadata() -> Result<()> {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>org.apache.maven</groupId>
+  <artifactId>apache-maven</artifactId>
+  <versioning>
+    <latest>4.0.0-rc-2</latest>
+    <release>4.0.0-rc-2</release>
+    <versions>
+      <version>3.8.4</version>
+      <version>3.9.6</version>
+      <version>4.0.0-rc-2</version>
+    </versions>
+    <lastUpdated>20241205101519</lastUpdated>
+  </versioning>
+</metadata>
+"#;
+        let versions = parse_metadata(content)?;
+        assert_eq!(
+            versions.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["3.8.4", "3.9.6", "4.0.0-rc-2"]
+        );
+        assert!(parse_metadata("<metadata></metadata>").is_err());
+        assert!(parse_metadata("not xml").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_digest() -> Result<()> {
+        let repo = Repository::new("https://repo.maven.apache.org/maven2/")?;
+        let bin_url = repo
+            .artifact_url()?
+            .join("3.9.6/apache-maven-3.9.6-bin.tar.gz")?;
+        assert_eq!(
+            digest_urls(&bin_url)?
+                .iter()
+                .map(|(_, url)| url.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "https://repo.maven.apache.org/maven2/org/apache/maven/apache-maven/3.9.6/apache-maven-3.9.6-bin.tar.gz.sha512",
+                "https://repo.maven.apache.org/maven2/org/apache/maven/apache-maven/3.9.6/apache-maven-3.9.6-bin.tar.gz.sha1",
+                "https://repo.maven.apache.org/maven2/org/apache/maven/apache-maven/3.9.6/apache-maven-3.9.6-bin.tar.gz.md5",
+            ]
+        );
+
+        let dir = tempfile::tempdir()?;
+        let repo = Repository::new(Url::from_directory_path(dir.path()).unwrap())?;
+        let ver_dir = dir.path().join(ARTIFACT_PATH).join("3.9.6");
+        std::fs::create_dir_all(&ver_dir)?;
+        let bin_url = repo
+            .artifact_url()?
+            .join("3.9.6/apache-maven-3.9.6-bin.zip")?;
+        assert!(repo.fetch_digest(&bin_url).await.is_err());
+
+        std::fs::write(ver_dir.join("apache-maven-3.9.6-bin.zip.md5"), "def456")?;
+        assert_eq!(
+            repo.fetch_digest(&bin_url).await?,
+            Some(Digest::Md5("def456".to_string()))
+        );
+        // 旧版本只有`.md5`与`.sha1`时使用`.sha1`
+        std::fs::write(ver_dir.join("apache-maven-3.9.6-bin.zip.sha1"), "abc123")?;
+        assert_eq!(
+            repo.fetch_digest(&bin_url).await?,
+            Some(Digest::Sha1("abc123".to_string()))
+        );
+        Ok(())
+    }
+}
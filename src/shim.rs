@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, trace};
+
+use crate::installs::Installs;
+
+/// 由shim接管的maven命令
+pub static SHIM_COMMANDS: [&str; 2] = ["mvn", "mvnDebug"];
+
+static SHIM_MARKER: &str = "# generated by mvnup shim";
+
+/// 可执行目录中由mvnup生成的`mvn`启动脚本。
+/// 脚本调用`mvnup exec`按当前目录选择版本后执行
+#[derive(Debug, Clone)]
+pub struct Shims {
+    dir: PathBuf,
+}
+
+impl Shims {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path(&self, cmd: &str) -> PathBuf {
+        self.dir.join(cmd)
+    }
+
+    /// `mvn`是否已是shim
+    pub fn is_enabled(&self) -> bool {
+        is_shim(&self.path(SHIM_COMMANDS[0]))
+    }
+
+    /// 为所有命令生成调用`mvnup_exe`的shim。
+    /// 只会覆盖已有的shim或指向`installs`的符号链接，不会覆盖其它文件
+    pub fn enable(&self, mvnup_exe: &Path, installs: &Installs) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        for cmd in SHIM_COMMANDS {
+            let path = self.path(cmd);
+            let managed = is_shim(&path)
+                || path
+                    .read_link()
+                    .map(|p| installs.version_of(&p).is_some())
+                    .unwrap_or(false);
+            if path.symlink_metadata().is_ok() && !managed {
+                bail!("{} exists and is not managed by mvnup", path.display());
+            }
+        }
+        for cmd in SHIM_COMMANDS {
+            let path = self.path(cmd);
+            debug!("writing shim {}", path.display());
+            let tmp = self
+                .dir
+                .join(format!(".{}.{}.tmp", cmd, std::process::id()));
+            fs::write(&tmp, script(mvnup_exe, cmd))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+            }
+            fs::rename(&tmp, &path).map_err(|e| {
+                let _ = fs::remove_file(&tmp);
+                anyhow!("failed to write shim {}: {}", path.display(), e)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 删除所有shim
+    pub fn disable(&self) -> Result<()> {
+        for cmd in SHIM_COMMANDS {
+            let path = self.path(cmd);
+            if is_shim(&path) {
+                debug!("removing shim {}", path.display());
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `path`是否是由mvnup生成的shim
+pub fn is_shim(path: &Path) -> bool {
+    let is_file = path
+        .symlink_metadata()
+        .map(|m| m.file_type().is_file())
+        .unwrap_or(false);
+    is_file
+        && fs::read_to_string(path)
+            .map(|s| s.contains(SHIM_MARKER))
+            .unwrap_or(false)
+}
+
+fn script(mvnup_exe: &Path, cmd: &str) -> String {
+    trace!("generating shim script for {}", cmd);
+    format!(
+        "#!/bin/sh\n{}\nexec {} exec {} -- \"$@\"\n",
+        SHIM_MARKER,
+        shell_quote(&mvnup_exe.to_string_lossy()),
+        cmd
+    )
+}
+
+/// 使用单引号转义shell参数，其中的`'`转换为`'\''`
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::InstallRecord;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_enable_and_disable() -> Result<()> {
+        let dir = tempdir()?;
+        let installs = Installs::new(dir.path().join("data"));
+        let shims = Shims::new(dir.path().join("bin"));
+        assert!(!shims.is_enabled());
+
+        shims.enable(Path::new("/usr/bin/mvnup"), &installs)?;
+        assert!(shims.is_enabled());
+        let content = fs::read_to_string(shims.path("mvnDebug"))?;
+        assert!(content.contains("exec '/usr/bin/mvnup' exec mvnDebug -- \"$@\""));
+
+        shims.disable()?;
+        assert!(!shims.is_enabled());
+        assert!(!shims.path("mvn").exists());
+
+        // 不覆盖其它文件
+        fs::write(shims.path("mvn"), "#!/bin/sh\n")?;
+        assert!(shims
+            .enable(Path::new("/usr/bin/mvnup"), &installs)
+            .is_err());
+
+        // 覆盖mvnup创建的链接
+        #[cfg(unix)]
+        {
+            fs::remove_file(shims.path("mvn"))?;
+            let ver = "3.8.4".parse::<crate::version::Version>()?;
+            installs.register(InstallRecord {
+                version: ver.clone(),
+                url: None,
+                filename: None,
+                digest: None,
+                home: installs.home(&ver),
+                links: vec![],
+                installed_at: chrono::Local::now(),
+            })?;
+            let exe = installs.exe(&ver);
+            std::os::unix::fs::symlink(&exe, shims.path("mvn"))?;
+            shims.enable(Path::new("/usr/bin/mvnup"), &installs)?;
+            assert!(shims.is_enabled());
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_quotes_exe() -> Result<()> {
+        use std::{os::unix::fs::PermissionsExt, process::Command};
+
+        let dir = tempdir()?;
+        let exe_dir = dir.path().join("it's \"$HOME\" `id`");
+        fs::create_dir_all(&exe_dir)?;
+        let exe = exe_dir.join("mvnup");
+        fs::write(&exe, "#!/bin/sh\necho \"$@\"\n")?;
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755))?;
+
+        let shim = dir.path().join("mvn");
+        fs::write(&shim, script(&exe, "mvn"))?;
+        let out = Command::new("sh").arg(&shim).arg("-v").output()?;
+        assert!(out.status.success(), "{:?}", out);
+        assert_eq!(String::from_utf8(out.stdout)?, "exec mvn -- -v\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, trace, warn};
+use pgp::{
+    types::{KeyId, PublicKeyTrait},
+    Deserializable, SignedPublicKey, StandaloneSignature,
+};
+
+static KEY_BEGIN: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+static KEY_END: &str = "-----END PGP PUBLIC KEY BLOCK-----";
+
+/// Apache项目发布的`KEYS`公钥集合
+///
+/// 如：https://archive.apache.org/dist/maven/KEYS
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    keys: Vec<SignedPublicKey>,
+}
+
+impl Keyring {
+    /// 解析`KEYS`文件内容。文件中可能有多个公钥块与`gpg --list-sigs`的文本混合
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut keys = vec![];
+        let mut rest = content;
+        while let Some(start) = rest.find(KEY_BEGIN) {
+            let end = rest[start..]
+                .find(KEY_END)
+                .map(|i| start + i + KEY_END.len())
+                .ok_or_else(|| anyhow!("unterminated public key block"))?;
+            match SignedPublicKey::from_string(&rest[start..end]) {
+                Ok((key, _)) => keys.push(key),
+                // 旧的KEYS中存在已不被支持的算法，忽略即可
+                Err(e) => warn!("skipped a unparseable public key: {}", e),
+            }
+            rest = &rest[end..];
+        }
+        if keys.is_empty() {
+            bail!("not found any public key");
+        }
+        debug!("found {} public keys", keys.len());
+        Ok(Self { keys })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        trace!("loading keyring from {}", path.display());
+        Self::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| anyhow!("failed to load keyring {}: {}", path.display(), e))
+    }
+
+    /// 使用ascii armored分离签名`signature`校验文件`path`，返回签名者主公钥的指纹
+    pub fn verify(&self, path: impl AsRef<Path>, signature: &str) -> Result<String> {
+        let path = path.as_ref();
+        let (sig, _) = StandaloneSignature::from_string(signature)
+            .map_err(|e| anyhow!("invalid signature: {}", e))?;
+        let issuers = sig.signature.issuer();
+        trace!("verifying {} signed by {:?}", path.display(), issuers);
+
+        let matches_issuer = |id: KeyId| issuers.is_empty() || issuers.contains(&&id);
+        for key in &self.keys {
+            let primary = &key.primary_key;
+            let mut verified =
+                matches_issuer(primary.key_id()) && sig.verify_file(primary, path).is_ok();
+            if !verified {
+                verified = key
+                    .public_subkeys
+                    .iter()
+                    .filter(|sub| matches_issuer(sub.key.key_id()))
+                    .any(|sub| sig.verify_file(&sub.key, path).is_ok());
+            }
+            if verified {
+                return Ok(hex::encode_upper(primary.fingerprint().as_bytes()));
+            }
+        }
+        bail!(
+            "bad signature for {}: not signed by any key of keyring",
+            path.display()
+        )
+    }
+}
+
+trait VerifyFile {
+    fn verify_file(&self, key: &impl PublicKeyTrait, path: &Path) -> Result<()>;
+}
+
+impl VerifyFile for StandaloneSignature {
+    fn verify_file(&self, key: &impl PublicKeyTrait, path: &Path) -> Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        self.signature.verify(key, reader).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static KEYS: &str = r#"This file contains the PGP keys of various developers.
+
+pub   ed25519 2026-07-05 [SC]
+      68329E48AC5FDA31FC42270BF7E0BAE4CB7DA764
+uid           [ultimate] mvnup test <test@example.com>
+
+-----BEGIN PGP PUBLIC KEY BLOCK-----
+
+mDMEatKlAhYJKwYBBAHaRw8BAQdACTvMSNYualyWO/G5++XTI0TSwtnWGBt2L1uS
+9TNSNMy0HW12bnVwIHRlc3QgPHRlc3RAZXhhbXBsZS5jb20+iJAEExYIADgWIQRo
+Mp5IrF/aMfxCJwv34Lrky32nZAUCatKlAgIbAwULCQgHAgYVCgkICwIEFgIDAQIe
+AQIXgAAKCRD34Lrky32nZLWxAP9cgpWxMHfApjvwgr6rP4H4pwu9Shvwo8KNej6W
+2IozKAEAycZXRcx5u6ZVuiEj0RCFQ44Rr0lSnMso5V9IfBSARAM=
+=Crpu
+-----END PGP PUBLIC KEY BLOCK-----
+"#;
+
+    static SIGNATURE: &str = r#"-----BEGIN PGP SIGNATURE-----
+
+iHUEABYIAB0WIQRoMp5IrF/aMfxCJwv34Lrky32nZAUCatKlAgAKCRD34Lrky32n
+ZOH+AQCguhKNyHXJe7f0qrtu32Rzjk9R0PQL5vX0mP5G1hwHKwEAuWrTx5Wl8N22
+QWDd1eXlBhRpSbSByi2Us9ImGnVwUwQ=
+=Wrrv
+-----END PGP SIGNATURE-----
+"#;
+
+    #[test]
+    fn test_verify() -> Result<()> {
+        let keyring = Keyring::parse(KEYS)?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("data.txt");
+
+        std::fs::write(&path, "hello maven")?;
+        assert_eq!(
+            keyring.verify(&path, SIGNATURE)?,
+            "68329E48AC5FDA31FC42270BF7E0BAE4CB7DA764"
+        );
+
+        std::fs::write(&path, "hello maven!")?;
+        assert!(keyring.verify(&path, SIGNATURE).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_empty_keys() {
+        assert!(Keyring::parse("no keys").is_err());
+    }
+}
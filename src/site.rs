@@ -1,31 +1,103 @@
-use crate::util::get_filename;
+use crate::{
+    archive::ArchiveFormat,
+    http,
+    listing::{parse_entries, read_dir, s3_listing_url, Entry, ListingFormat},
+    source::Source,
+    util::{get_filename, local_path},
+    version::Version,
+};
 use anyhow::{anyhow, bail, Error, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Local};
-use futures_util::{future::join_all, try_join, StreamExt};
+use futures_util::{
+    future::join_all,
+    stream::{self, BoxStream},
+    try_join, StreamExt,
+};
 use getset::Getters;
 use log::{debug, error, info, log_enabled, trace, warn};
+use md5::Md5;
 use mime::Mime;
-use once_cell::sync::Lazy;
-use reqwest::Client;
-use scraper::{Html, Selector};
-use semver::Version;
-use std::{fmt::Display, path::Path, time::Duration};
+use reqwest::{
+    header::{HeaderMap, HeaderName, CONTENT_LENGTH, CONTENT_TYPE, LAST_MODIFIED, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest as _, Sha512};
+use std::{fmt::Display, path::Path};
 use strum::{AsRefStr, EnumString, EnumVariantNames, VariantNames};
-use tokio::{fs as afs, io::AsyncWriteExt};
+use tokio::{
+    fs as afs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
 use url::Url;
 
-pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .timeout(Duration::from_secs(4))
-        .build()
-        .expect("build client failed")
-});
-
-#[derive(Debug, Clone, PartialEq, Eq, EnumVariantNames, EnumString, AsRefStr)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumVariantNames, EnumString, AsRefStr, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum Digest {
     Sha512(String),
-    Md5(String),
     Sha1(String),
+    Md5(String),
+}
+
+impl Digest {
+    /// 摘要的16进制值
+    pub fn value(&self) -> &str {
+        match self {
+            Digest::Sha512(s) | Digest::Sha1(s) | Digest::Md5(s) => s,
+        }
+    }
+
+    /// 创建与当前摘要算法对应的hasher
+    pub fn hasher(&self) -> DigestHasher {
+        match self {
+            Digest::Sha512(_) => DigestHasher::Sha512(Sha512::new()),
+            Digest::Md5(_) => DigestHasher::Md5(Md5::new()),
+            Digest::Sha1(_) => DigestHasher::Sha1(Sha1::new()),
+        }
+    }
+
+    /// 检查计算出的摘要`actual`是否与当前摘要一致
+    pub fn verify(&self, actual: &str) -> Result<()> {
+        if !self.value().eq_ignore_ascii_case(actual) {
+            bail!(
+                "{} digest mismatch. expected: {}, actual: {}",
+                self.as_ref().to_ascii_lowercase(),
+                self.value(),
+                actual
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 流式计算文件摘要
+pub enum DigestHasher {
+    Sha512(Sha512),
+    Md5(Md5),
+    Sha1(Sha1),
+}
+
+impl DigestHasher {
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            DigestHasher::Sha512(h) => h.update(data),
+            DigestHasher::Md5(h) => h.update(data),
+            DigestHasher::Sha1(h) => h.update(data),
+        }
+    }
+
+    /// 返回16进制小写的摘要值
+    pub fn finalize(self) -> String {
+        match self {
+            DigestHasher::Sha512(h) => hex::encode(h.finalize()),
+            DigestHasher::Md5(h) => hex::encode(h.finalize()),
+            DigestHasher::Sha1(h) => hex::encode(h.finalize()),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Getters)]
@@ -33,28 +105,107 @@ pub enum Digest {
 pub struct BinFile {
     url: Url,
     filename: String,
-    last_modified: DateTime<Local>,
-    size: usize,
+    /// 服务器没有返回`Last-Modified`时为`None`
+    last_modified: Option<DateTime<Local>>,
+    /// 服务器没有返回`Content-Length`时为`None`，只能依靠摘要校验
+    size: Option<usize>,
     mime: Mime,
     digest: Option<Digest>,
+    /// 分离签名文件`.asc`的url
+    signature: Option<Url>,
 }
 
 impl BinFile {
-    pub async fn download(&self, path: impl AsRef<Path>) -> Result<()> {
-        let path = path.as_ref();
-        trace!("starting download to {} for {}", path.display(), self.url());
-        let mut file = afs::File::create(path).await?;
-        let resp = reqwest::get(self.url.clone()).await?;
+    /// 使用head请求获取`url`的文件元数据
+    pub async fn fetch(url: Url, digest: Option<Digest>, signature: Option<Url>) -> Result<Self> {
+        let (filename, mime, size, last_modified) = fetch_bin_metadata(&url).await?;
+        Ok(Self {
+            url,
+            filename,
+            last_modified,
+            size,
+            mime,
+            digest,
+            signature,
+        })
+    }
+
+    /// 获取ascii armored格式的OpenPGP签名
+    pub async fn fetch_signature(&self) -> Result<String> {
+        let url = self
+            .signature
+            .clone()
+            .ok_or_else(|| anyhow!("not found signature for {}", self.filename))?;
+        fetch_cxt(url).await
+    }
+
+    /// 打开文件内容的字节流。`file://`直接读取本地文件。
+    /// `range`为`Some(n)`时只请求开头的`n`个字节，服务器可能忽略该范围
+    async fn open_stream(&self, range: Option<u64>) -> Result<BoxStream<'static, Result<Vec<u8>>>> {
+        if let Some(path) = local_path(&self.url) {
+            debug!(
+                "copying local file {}, size: {:?}",
+                path.display(),
+                self.size
+            );
+            let file = afs::File::open(&path)
+                .await
+                .map_err(|e| anyhow!("failed to open {}: {}", path.display(), e))?;
+            return Ok(stream::try_unfold(file, |mut file| async move {
+                let mut buf = vec![0; 64 * 1024];
+                let n = file.read(&mut buf).await?;
+                buf.truncate(n);
+                Ok((n > 0).then_some((buf, file)))
+            })
+            .boxed());
+        }
+        let mut req = http::get(&self.url);
+        if let Some(n) = range.filter(|n| *n > 0) {
+            req = req.header(RANGE, format!("bytes=0-{}", n - 1));
+        }
+        let resp = http::read(req.send()).await?;
         debug!(
-            "downloading file content length: {:?}, size: {}",
+            "downloading file content length: {:?}, size: {:?}",
             resp.content_length(),
             self.size
         );
-        let mut stream = resp.bytes_stream();
+        if !resp.status().is_success() {
+            bail!("failed to download {}. status: {}", self.url, resp.status());
+        }
+        Ok(stream::try_unfold(resp, |mut resp| async move {
+            let chunk = http::read(resp.chunk()).await?;
+            Ok(chunk.map(|b| (b.to_vec(), resp)))
+        })
+        .boxed())
+    }
+
+    /// 读取文件开头最多`len`个字节并丢弃，返回读取的字节数。用于测量下载速度
+    pub async fn read_head(&self, len: u64) -> Result<u64> {
+        let mut stream = self.open_stream(Some(len)).await?;
+        let mut read = 0;
+        while read < len {
+            match stream.next().await {
+                Some(chunk) => read += chunk?.len() as u64,
+                None => break,
+            }
+        }
+        Ok(read.min(len))
+    }
+
+    pub async fn download(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        trace!("starting download to {} for {}", path.display(), self.url());
+        let mut stream = self.open_stream(None).await?;
+        let mut file = afs::File::create(path).await?;
+        let mut hasher = self.digest.as_ref().map(Digest::hasher);
         while let Some(chunk) = stream.next().await {
-            let mut chunk = chunk?;
-            file.write_all_buf(&mut chunk).await?;
+            let chunk = chunk?;
+            if let Some(h) = hasher.as_mut() {
+                h.update(&chunk);
+            }
+            file.write_all(&chunk).await?;
         }
+        file.flush().await?;
         if log_enabled!(log::Level::Info) {
             info!(
                 "download completed. file {} size: {}",
@@ -62,10 +213,15 @@ impl BinFile {
                 file.metadata().await?.len()
             );
         }
-        // self.digest.map(|digest| digest.check(s))
-        if let Some(_d) = self.digest() {
-            // todo!()
-            return Ok(());
+        if let (Some(digest), Some(hasher)) = (self.digest(), hasher) {
+            let actual = hasher.finalize();
+            trace!("checking digest {} for {}", actual, path.display());
+            if let Err(e) = digest.verify(&actual) {
+                drop(file);
+                afs::remove_file(path).await?;
+                bail!("{} is corrupted: {}", self.filename, e);
+            }
+            debug!("{} digest verified", path.display());
         } else {
             warn!("{} digests not checked", path.display());
         }
@@ -73,7 +229,16 @@ impl BinFile {
     }
 }
 
+/// 镜像中`maven/`下的发布线目录
+pub static RELEASE_LINES: [&str; 2] = ["maven-3", "maven-4"];
+
+/// 版本`ver`所在的发布线目录。如：`4.0.0-rc-1`在`maven-4`中
+pub fn release_line(ver: &Version) -> String {
+    format!("maven-{}", ver.major())
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[getset(get = "pub")]
 pub struct Site {
     mirror: Url,
 }
@@ -88,33 +253,79 @@ impl Site {
         Ok(Self { mirror })
     }
 
-    /// 获取版本信息
-    pub async fn fetch_versions(&self) -> Result<Vec<Version>> {
-        let url = self.mirror.join("maven/maven-3/")?;
+    /// 获取发布线`line`的版本信息。如：`maven-3`
+    pub async fn fetch_line_versions(&self, line: &str) -> Result<Vec<Version>> {
+        let url = self.mirror.join(&format!("maven/{}/", line))?;
         debug!("fetching versions from {}", url);
-        parse_versions(&reqwest::get(url).await?.text().await?)
+        Ok(parse_versions(&fetch_entries(&url).await?))
+    }
+}
+
+#[async_trait]
+impl Source for Site {
+    fn url(&self) -> &Url {
+        &self.mirror
+    }
+
+    /// 获取所有发布线的版本信息。忽略镜像中不存在的发布线
+    async fn fetch_versions(&self) -> Result<Vec<Version>> {
+        let mut versions = vec![];
+        let mut last_err = None;
+        for res in join_all(
+            RELEASE_LINES
+                .iter()
+                .map(|line| self.fetch_line_versions(line)),
+        )
+        .await
+        {
+            match res {
+                Ok(vers) => versions.extend(vers),
+                Err(e) => {
+                    warn!("failed to fetch versions: {}", e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        if versions.is_empty() {
+            if let Some(e) = last_err {
+                return Err(e);
+            }
+        }
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        versions.dedup();
+        Ok(versions)
+    }
+
+    async fn fetch_digest(&self, bin_url: &Url) -> Result<Option<Digest>> {
+        fetch_digest(bin_url).await
+    }
+
+    /// 获取maven发布者的公钥文件`KEYS`
+    async fn fetch_keys(&self) -> Result<String> {
+        fetch_cxt(self.mirror.join("maven/KEYS")?).await
     }
 
     /// 获取binaries中的文件信息
-    pub async fn fetch_bins(&self, ver: Version) -> Result<Vec<BinFile>> {
+    async fn fetch_bins(&self, ver: &Version) -> Result<Vec<BinFile>> {
         // find binaries info
-        // let ver = ver.as_ref().parse::<Version>()?;
         let url = self
             .mirror
-            .join(&format!("maven/maven-3/{}/binaries/", ver))?;
+            .join(&format!("maven/{}/{}/binaries/", release_line(ver), ver))?;
 
         // concurrent
         debug!("fetching {} binaries for {}", ver, url);
-        let content = HTTP_CLIENT.get(url.clone()).send().await?.text().await?;
-        let tasks = parse_bin_names(&content)?
+        let entries = fetch_entries(&url).await?;
+        let files = file_names(&entries);
+        let tasks = parse_bin_names(&entries)?
             .into_iter()
             .map(|name| url.join(&name).map_err::<Error, _>(Into::into))
             .map(|bin_url| {
                 bin_url.map(|url| {
-                    let content = content.clone();
+                    let files = &files;
                     async move {
                         trace!("fetching metadata and digest for {} in concurrent", url);
-                        try_join!(fetch_bin_metadata(&url), fetch_bin_digest(&url, &content)).map(
+                        let signature = find_signature(&url, files)?;
+                        try_join!(fetch_bin_metadata(&url), fetch_bin_digest(&url, files)).map(
                             |((filename, mime, size, last_modified), digest)| BinFile {
                                 digest,
                                 filename,
@@ -122,6 +333,7 @@ impl Site {
                                 mime,
                                 size,
                                 url,
+                                signature,
                             },
                         )
                     }
@@ -137,15 +349,16 @@ impl Site {
 
 /// 对url使用head请求获取binaries文件元数据
 /// 如：https://archive.apache.org/dist/maven/maven-3/3.8.4/binaries/apache-maven-3.8.4-bin.tar.gz
-async fn fetch_bin_metadata(url: &Url) -> Result<(String, Mime, usize, DateTime<Local>)> {
+async fn fetch_bin_metadata(
+    url: &Url,
+) -> Result<(String, Mime, Option<usize>, Option<DateTime<Local>>)> {
     // parse http headers
-    let filename = get_filename(&url)?;
+    let filename = get_filename(url)?;
+    if let Some(path) = local_path(url) {
+        return read_bin_metadata(&path, filename).await;
+    }
     debug!("fetching bin metadata {} for {}", filename, url);
-    let resp = reqwest::Client::builder()
-        .build()?
-        .head(url.as_str())
-        .send()
-        .await?;
+    let resp = http::read(http::head(url).send()).await?;
     if !resp.status().is_success() {
         bail!(
             "failed to response status {} for {}",
@@ -154,119 +367,233 @@ async fn fetch_bin_metadata(url: &Url) -> Result<(String, Mime, usize, DateTime<
         );
     }
 
-    // parse headers
-    let headers = resp.headers();
+    let (mime, size, last_modified) = parse_bin_headers(resp.headers());
+    Ok((filename, mime, size, last_modified))
+}
+
+/// 从响应头中解析文件元数据。制品库常省略这些响应头，缺少或无效时忽略：
+/// `Content-Type`默认为`application/octet-stream`，大小与修改时间为`None`
+fn parse_bin_headers(headers: &HeaderMap) -> (Mime, Option<usize>, Option<DateTime<Local>>) {
     trace!("parsing mvn files info in headers: {:?}", headers);
-    let parse_header = |name| {
-        if let Some(val) = headers.get(name) {
-            trace!("parsing header {}={:?}", name, val);
-            val.to_str().map_err(Into::into)
-        } else {
-            bail!("not found header: {}", name)
+    let header = |name: HeaderName| {
+        let val = headers.get(&name).and_then(|v| v.to_str().ok());
+        if val.is_none() {
+            debug!("not found valid header {}", name);
         }
+        val
     };
+    let mime = header(CONTENT_TYPE)
+        .and_then(|s| s.parse::<Mime>().ok())
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    let size = header(CONTENT_LENGTH).and_then(|s| s.parse::<usize>().ok());
+    let last_modified = header(LAST_MODIFIED)
+        .and_then(|s| DateTime::parse_from_rfc2822(s).ok())
+        .map(|d| d.with_timezone(&Local));
+    (mime, size, last_modified)
+}
 
-    let mime = parse_header("Content-Type")?.parse::<Mime>()?;
-    let size = parse_header("Content-Length")?.parse::<usize>()?;
-    let last_modified = parse_header("Last-Modified")
-        .and_then(|s| DateTime::parse_from_rfc2822(s).map_err(Into::into))
-        .map(|d| d.with_timezone(&Local))?;
-    Ok((filename, mime, size, last_modified))
+/// 从文件系统读取本地文件`path`的元数据。根据文件内容识别mime
+async fn read_bin_metadata(
+    path: &Path,
+    filename: String,
+) -> Result<(String, Mime, Option<usize>, Option<DateTime<Local>>)> {
+    debug!("reading bin metadata {} for {}", filename, path.display());
+    let meta = afs::metadata(path)
+        .await
+        .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+    if !meta.is_file() {
+        bail!("{} is not a file", path.display());
+    }
+    let mime = infer::get_from_path(path)?
+        .map(|t| t.mime_type())
+        .unwrap_or("application/octet-stream")
+        .parse::<Mime>()?;
+    let last_modified = DateTime::<Local>::from(meta.modified()?);
+    Ok((
+        filename,
+        mime,
+        Some(meta.len() as usize),
+        Some(last_modified),
+    ))
 }
 
-async fn fetch_cxt(url: Url) -> Result<String> {
+/// 获取`url`的文本内容。`file://`直接读取本地文件
+pub async fn fetch_cxt(url: Url) -> Result<String> {
+    if let Some(path) = local_path(&url) {
+        trace!("reading content of {}", path.display());
+        return afs::read_to_string(&path)
+            .await
+            .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e));
+    }
     let dup_url = url.to_string();
-    trace!("fetching digest content for {}", url);
-    let filename = get_filename(&url)?;
-    let resp = reqwest::get(url.as_str()).await?;
+    trace!("fetching content of {}", url);
+    let resp = http::read(http::get(&url).send()).await?;
     if !resp.status().is_success() {
         trace!(
-            "failed to fetch digest for {}. status: {}, headers: {:?}",
+            "failed to fetch content of {}. status: {}, headers: {:?}",
             url,
             resp.status(),
             resp.headers()
         );
         bail!(
-            "failed to get digest {}. status: {}",
-            filename,
+            "failed to get content of {}. status: {}",
+            url,
             resp.status()
         );
     }
-    debug!("found digest for {}", filename);
-    resp.text().await.map_err(Into::into).map_err(move |e| {
-        info!("failed to fetch digest for {}: {}", dup_url, e);
+    debug!("found content of {}", url);
+    http::read(resp.text()).await.map_err(move |e| {
+        info!("failed to fetch content of {}: {}", dup_url, e);
         e
     })
 }
 
+/// 目录列表中的所有文件名
+fn file_names(entries: &[Entry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|e| !e.dir())
+        .map(|e| e.name().to_string())
+        .collect()
+}
+
 /// 解析页面`https://archive.apache.org/dist/maven/maven-3/3.8.4/binaries/`中的版本文件名
-fn parse_bin_names(content: &str) -> Result<Vec<String>> {
-    trace!("parsing bin names in content size: {}", content.len());
-    let html = Html::parse_document(content);
-    let link_selector = Selector::parse("img[alt*='[  ']+a").map_err(|e| {
-        anyhow!(
-            "failed to parsing. kind: {:?}, location: {:?}",
-            e.kind,
-            e.location
-        )
-    })?;
-    let names = html
-        .select(&link_selector)
-        .map(|e| e.inner_html().trim().to_string())
+fn parse_bin_names(entries: &[Entry]) -> Result<Vec<String>> {
+    trace!("parsing bin names in entries: {}", entries.len());
+    let names = file_names(entries)
+        .into_iter()
+        .filter(|name| ArchiveFormat::from_filename(name).is_some())
         .collect::<Vec<_>>();
     if names.is_empty() {
-        error!("failed to parse bin names empty in content: {}", content);
+        error!("failed to parse bin names empty in entries: {:?}", entries);
         bail!("not found bin names");
     }
     Ok(names)
 }
 
-async fn fetch_bin_digest(bin_url: &Url, content: &str) -> Result<Option<Digest>> {
-    let bin_name = Path::new(bin_url.path())
-        .file_name()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| anyhow!("not found filename for {}", bin_url))?;
-    let content = content.to_ascii_lowercase();
+async fn fetch_bin_digest(bin_url: &Url, files: &[String]) -> Result<Option<Digest>> {
+    let bin_name = get_filename(bin_url)?;
     for ext_name in Digest::VARIANTS {
-        let digest_filename = format!("{}.{}", bin_name, ext_name).to_ascii_lowercase();
-        if content.contains(&digest_filename) {
-            let digest_url = bin_url.join(&digest_filename)?;
+        let digest_filename = format!("{}.{}", bin_name, ext_name);
+        if let Some(name) = files
+            .iter()
+            .find(|f| f.eq_ignore_ascii_case(&digest_filename))
+        {
+            let digest_url = bin_url.join(name)?;
             let cxt = fetch_cxt(digest_url).await?;
-            let mut digest = ext_name.parse::<Digest>()?;
-            match &mut digest {
-                Digest::Md5(s) => {
-                    s.push_str(&cxt);
-                }
-                Digest::Sha1(s) => {
-                    s.push_str(&cxt);
-                }
-                Digest::Sha512(s) => {
-                    s.push_str(&cxt);
-                }
+            return parse_digest(ext_name, &cxt).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// `bin_url`的摘要文件地址，按`.sha512`、`.sha1`、`.md5`的顺序。扩展名总是小写
+pub fn digest_urls(bin_url: &Url) -> Result<Vec<(&'static str, Url)>> {
+    let bin_name = get_filename(bin_url)?;
+    Digest::VARIANTS
+        .iter()
+        .map(|ext_name| {
+            let url = bin_url.join(&format!("{}.{}", bin_name, ext_name.to_ascii_lowercase()))?;
+            Ok((*ext_name, url))
+        })
+        .collect()
+}
+
+/// 依次尝试获取`bin_url`的`.sha512`等摘要文件。所有摘要文件都不存在时返回`None`
+pub async fn fetch_digest(bin_url: &Url) -> Result<Option<Digest>> {
+    for (ext_name, digest_url) in digest_urls(bin_url)? {
+        trace!("trying digest {}", digest_url);
+        if let Some(path) = local_path(&digest_url) {
+            if path.is_file() {
+                return parse_digest(ext_name, &afs::read_to_string(&path).await?).map(Some);
             }
-            return Ok(Some(digest));
+            continue;
         }
+        let resp = http::read(http::get(&digest_url).send()).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !resp.status().is_success() {
+            bail!(
+                "failed to get digest {}. status: {}",
+                digest_url,
+                resp.status()
+            );
+        }
+        return parse_digest(ext_name, &http::read(resp.text()).await?).map(Some);
     }
+    debug!("not found any digest for {}", bin_url);
     Ok(None)
 }
 
-/// 从html中解析出版本信息
-fn parse_versions(content: &str) -> Result<Vec<Version>> {
-    trace!("parsing versions in content {}", content.len());
-    let html = Html::parse_document(content);
-    let link_selector = Selector::parse("img[alt='[DIR]']+a").map_err(|e| {
-        anyhow!(
-            "failed to parsing. kind: {:?}, location: {:?}",
-            e.kind,
-            e.location
-        )
-    })?;
-    let versions = html
-        .select(&link_selector)
-        .map(|e| e.inner_html().trim().replace("/", ""))
-        .flat_map(|e| e.parse::<Version>())
-        .collect::<Vec<_>>();
-    Ok(versions)
+/// 获取目录`url`中的条目。`file://`直接读取本地目录
+async fn fetch_entries(url: &Url) -> Result<Vec<Entry>> {
+    match local_path(url) {
+        Some(path) => read_dir(path),
+        None => parse_entries(&fetch_listing(url).await?),
+    }
+}
+
+/// 获取目录列表页面`url`的内容。
+/// 目录不可访问时尝试作为S3 bucket列出，S3不支持直接访问目录
+async fn fetch_listing(url: &Url) -> Result<String> {
+    let resp = http::read(http::get(url).send()).await?;
+    if resp.status().is_success() {
+        return http::read(resp.text()).await;
+    }
+    let status = resp.status();
+    let s3_url = s3_listing_url(url)?;
+    trace!(
+        "failed to fetch listing {}. status: {}. trying s3 listing {}",
+        url,
+        status,
+        s3_url
+    );
+    let resp = http::read(http::get(&s3_url).send()).await?;
+    if resp.status().is_success() {
+        let content = http::read(resp.text()).await?;
+        if ListingFormat::detect(&content) == ListingFormat::S3 {
+            return Ok(content);
+        }
+    }
+    bail!("failed to fetch {}. status: {}", url, status)
+}
+
+/// 查找binaries目录中与`bin_url`对应的`.asc`签名文件，与摘要文件一样忽略大小写
+fn find_signature(bin_url: &Url, files: &[String]) -> Result<Option<Url>> {
+    let sig_filename = format!("{}.asc", get_filename(bin_url)?);
+    files
+        .iter()
+        .find(|f| f.eq_ignore_ascii_case(&sig_filename))
+        .map(|name| bin_url.join(name))
+        .transpose()
+        .map_err(Into::into)
+}
+
+/// 解析摘要文件内容。兼容`sha512sum`输出的`<digest>  <filename>`格式
+pub fn parse_digest(ext_name: &str, content: &str) -> Result<Digest> {
+    let value = content
+        .split_whitespace()
+        .next()
+        .filter(|s| s.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|s| s.to_ascii_lowercase())
+        .ok_or_else(|| anyhow!("invalid {} digest content: {}", ext_name, content))?;
+    let mut digest = ext_name.parse::<Digest>()?;
+    match &mut digest {
+        Digest::Md5(s) | Digest::Sha1(s) | Digest::Sha512(s) => {
+            s.push_str(&value);
+        }
+    }
+    Ok(digest)
+}
+
+/// 从目录列表中解析出版本信息
+fn parse_versions(entries: &[Entry]) -> Vec<Version> {
+    entries
+        .iter()
+        .filter(|e| *e.dir())
+        .flat_map(|e| e.name().parse::<Version>())
+        .collect()
 }
 
 #[cfg(test)]
@@ -291,10 +618,11 @@ mod tests {
         BinFile {
             url: "https://archive.apache.org/dist/maven/maven-3/3.8.4/binaries/apache-maven-3.8.4-bin.tar.gz".parse::<Url>().unwrap(),
             filename: "apache-maven-3.8.4-bin.tar.gz".to_string(),
-            last_modified: DateTime::parse_from_rfc2822("Sun, 14 Nov 2021 13:25:01 GMT").unwrap().with_timezone(&Local),
-            size: 9046177,
+            last_modified: Some(DateTime::parse_from_rfc2822("Sun, 14 Nov 2021 13:25:01 GMT").unwrap().with_timezone(&Local)),
+            size: Some(9046177),
             digest: Some(Digest::Sha512("a9b2d825eacf2e771ed5d6b0e01398589ac1bfa4171f36154d1b5787879605507802f699da6f7cfc80732a5282fd31b28e4cd6052338cbef0fa1358b48a5e3c8".to_string())),
-            mime: "application/x-gzip".parse().unwrap()
+            mime: "application/x-gzip".parse().unwrap(),
+            signature: Some("https://archive.apache.org/dist/maven/maven-3/3.8.4/binaries/apache-maven-3.8.4-bin.tar.gz.asc".parse::<Url>().unwrap()),
         }
     });
 
@@ -338,7 +666,7 @@ mod tests {
 <hr></pre>
 </body></html>
         "#;
-        let versions = parse_versions(body)?;
+        let versions = parse_versions(&parse_entries(body)?);
         assert_eq!(versions.len(), 26);
         assert_eq!(
             versions.first().map(ToString::to_string),
@@ -368,9 +696,16 @@ mod tests {
 <hr></pre>
 </body></html>"#;
 
+    #[test]
+    fn test_release_line() -> Result<()> {
+        assert_eq!(release_line(&"3.8.4".parse()?), "maven-3");
+        assert_eq!(release_line(&"4.0.0-rc-1".parse()?), "maven-4");
+        Ok(())
+    }
+
     #[test]
     fn test_parse_bin_names() -> Result<()> {
-        let names = parse_bin_names(CONTENT)?;
+        let names = parse_bin_names(&parse_entries(CONTENT)?)?;
         assert_eq!(names.len(), 2);
         assert!(names.contains(&"apache-maven-3.8.4-bin.tar.gz".to_string()));
         assert!(names.contains(&"apache-maven-3.8.4-bin.zip".to_string()));
@@ -378,6 +713,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_digest() -> Result<()> {
+        let hash = "a9b2d825eacf2e771ed5d6b0e01398589ac1bfa4171f36154d1b5787879605507802f699da6f7cfc80732a5282fd31b28e4cd6052338cbef0fa1358b48a5e3c8";
+        assert_eq!(
+            parse_digest("Sha512", hash)?,
+            BIN_FILE.digest.clone().unwrap()
+        );
+        assert_eq!(
+            parse_digest(
+                "Sha512",
+                &format!("{}  apache-maven-3.8.4-bin.tar.gz\n", hash.to_uppercase())
+            )?,
+            BIN_FILE.digest.clone().unwrap()
+        );
+        assert!(parse_digest("Sha1", "<html>not found</html>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_signature() -> Result<()> {
+        let bin = BIN_FILE.clone();
+        let files = file_names(&parse_entries(CONTENT)?);
+        assert_eq!(&find_signature(bin.url(), &files)?, bin.signature());
+        let url = bin.url().join("apache-maven-3.8.4-src.tar.gz")?;
+        assert_eq!(find_signature(&url, &files)?, None);
+
+        let files = vec!["apache-maven-3.8.4-bin.tar.gz.ASC".to_string()];
+        assert_eq!(
+            find_signature(bin.url(), &files)?,
+            Some(bin.url().join(&files[0])?)
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_fetch_bin_metadata() -> Result<()> {
         let bin = BIN_FILE.clone();
@@ -390,14 +759,76 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_bin_headers() -> Result<()> {
+        let (mime, size, last_modified) = parse_bin_headers(&HeaderMap::new());
+        assert_eq!(mime, mime::APPLICATION_OCTET_STREAM);
+        assert_eq!(size, None);
+        assert_eq!(last_modified, None);
+
+        let bin = BIN_FILE.clone();
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/x-gzip".parse()?);
+        headers.insert(CONTENT_LENGTH, "9046177".parse()?);
+        headers.insert(LAST_MODIFIED, "Sun, 14 Nov 2021 13:25:01 GMT".parse()?);
+        assert_eq!(
+            parse_bin_headers(&headers),
+            (bin.mime, bin.size, bin.last_modified)
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_fetch_bin_digest() -> Result<()> {
         let bin = BIN_FILE.clone();
-        let res = fetch_bin_digest(bin.url(), CONTENT).await?;
+        let res = fetch_bin_digest(bin.url(), &file_names(&parse_entries(CONTENT)?)).await?;
         assert_eq!(res, bin.digest);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_local_mirror() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let bin_dir = dir.path().join("maven/maven-3/3.8.4/binaries");
+        std::fs::create_dir_all(&bin_dir)?;
+        std::fs::create_dir_all(dir.path().join("maven/maven-3/3.6.3"))?;
+        let content = b"fake maven archive";
+        let bin_name = "apache-maven-3.8.4-bin.tar.gz";
+        std::fs::write(bin_dir.join(bin_name), content)?;
+        let mut hasher = Sha512::new();
+        hasher.update(content);
+        let hash = hex::encode(hasher.finalize());
+        std::fs::write(
+            bin_dir.join(format!("{}.sha512", bin_name)),
+            format!("{}  {}\n", hash, bin_name),
+        )?;
+        std::fs::write(bin_dir.join(format!("{}.asc", bin_name)), "sig")?;
+
+        let site = Site::new(Url::from_directory_path(dir.path()).unwrap())?;
+        let versions = site.fetch_versions().await?;
+        assert_eq!(
+            versions.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["3.8.4", "3.6.3"]
+        );
+
+        let bins = site.fetch_bins(&"3.8.4".parse()?).await?;
+        assert_eq!(bins.len(), 1);
+        let bin = &bins[0];
+        assert_eq!(bin.filename(), bin_name);
+        assert_eq!(*bin.size(), Some(content.len()));
+        assert_eq!(bin.digest(), &Some(Digest::Sha512(hash)));
+        assert_eq!(bin.fetch_signature().await?, "sig");
+
+        let down_path = dir.path().join("download.tar.gz");
+        bin.download(&down_path).await?;
+        assert_eq!(std::fs::read(&down_path)?, content);
+
+        std::fs::write(bin_dir.join(bin_name), b"corrupted")?;
+        assert!(bin.download(&down_path).await.is_err());
+        assert!(!down_path.exists());
+        Ok(())
+    }
+
     #[cfg(test)]
     mod binfile_tests {
 
@@ -431,7 +862,7 @@ mod tests {
         async fn test_fetch_bins() -> Result<()> {
             let site = ARCHIVE_SITE.clone();
             let ver = "3.8.4";
-            let bins = site.fetch_bins(ver.parse::<Version>()?).await?;
+            let bins = site.fetch_bins(&ver.parse()?).await?;
             assert_eq!(bins.len(), 2);
             bins.iter().for_each(|f| assert!(f.filename.contains(ver)));
             Ok(())
@@ -0,0 +1,239 @@
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Error, Result};
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use log::{info, warn};
+use strum::{AsRefStr, EnumString, EnumVariantNames};
+use url::Url;
+
+use crate::{
+    closer::{AutoSite, ARCHIVE_URL},
+    repository::Repository,
+    site::{BinFile, Digest, Site},
+    util::parse_mirror,
+    version::Version,
+};
+
+/// maven发布文件的来源
+#[async_trait]
+pub trait Source: Debug + Send + Sync {
+    /// 来源的根地址
+    fn url(&self) -> &Url;
+
+    /// 获取所有版本，按版本降序
+    async fn fetch_versions(&self) -> Result<Vec<Version>>;
+
+    /// 获取版本`ver`的二进制压缩文件
+    async fn fetch_bins(&self, ver: &Version) -> Result<Vec<BinFile>>;
+
+    /// 获取文件`bin_url`的摘要。没有摘要文件时返回`None`
+    async fn fetch_digest(&self, bin_url: &Url) -> Result<Option<Digest>>;
+
+    /// 获取maven发布者的公钥文件`KEYS`
+    async fn fetch_keys(&self) -> Result<String> {
+        bail!("{} does not provide KEYS. use `--keys` instead", self.url())
+    }
+}
+
+/// 来源的目录结构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, EnumVariantNames, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Layout {
+    /// apache发布目录，如：https://archive.apache.org/dist/
+    Dist,
+    /// maven仓库，如：https://repo.maven.apache.org/maven2/
+    Repository,
+}
+
+impl Layout {
+    pub fn source(&self, mirror: Mirror) -> Result<Box<dyn Source>> {
+        Ok(match (self, mirror) {
+            (Layout::Dist, Mirror::Auto) => Box::new(AutoSite::new()?),
+            (Layout::Dist, Mirror::Url(url)) => Box::new(Site::new(url)?),
+            (Layout::Repository, Mirror::Url(url)) => Box::new(Repository::new(url)?),
+            (Layout::Repository, Mirror::Auto) => {
+                bail!("`auto` mirror only supports the dist layout")
+            }
+        })
+    }
+}
+
+/// 命令行或配置中的镜像
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mirror {
+    /// 通过apache镜像选择器自动选择最近的镜像
+    Auto,
+    Url(Url),
+}
+
+impl FromStr for Mirror {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            Ok(Mirror::Auto)
+        } else {
+            parse_mirror(s).map(Mirror::Url)
+        }
+    }
+}
+
+impl Display for Mirror {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mirror::Auto => write!(f, "auto"),
+            Mirror::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// 合并命令行与配置中使用`layout`的镜像`explicit`及`settings.xml`中的镜像`extra`，去除重复。
+/// 没有指定`explicit`时，归档站点总是作为最后的后备
+pub fn merge_mirrors(
+    explicit: Vec<Mirror>,
+    layout: Layout,
+    extra: Vec<(Mirror, Layout)>,
+) -> Result<Vec<(Mirror, Layout)>> {
+    let fallback = if explicit.is_empty() {
+        Some((Mirror::Url(ARCHIVE_URL.parse()?), Layout::Dist))
+    } else {
+        None
+    };
+    let mut mirrors = vec![];
+    for mirror in explicit
+        .into_iter()
+        .map(|m| (m, layout))
+        .chain(extra)
+        .chain(fallback)
+    {
+        if !mirrors.contains(&mirror) {
+            mirrors.push(mirror);
+        }
+    }
+    Ok(mirrors)
+}
+
+/// 按顺序使用的多个镜像。请求失败时尝试下一个镜像，如连接错误、5xx响应或文件不存在
+#[derive(Debug)]
+pub struct Mirrors {
+    sources: Vec<Box<dyn Source>>,
+}
+
+impl Mirrors {
+    pub fn new(sources: Vec<Box<dyn Source>>) -> Result<Self> {
+        if sources.is_empty() {
+            bail!("no mirror configured");
+        }
+        Ok(Self { sources })
+    }
+
+    pub fn sources(&self) -> &[Box<dyn Source>] {
+        &self.sources
+    }
+
+    /// 依次在每个镜像上执行`op`，返回第一个成功的结果。
+    /// 由后备镜像成功时提示用户实际使用的镜像。只有一个镜像时直接返回其错误
+    pub async fn try_each<'a, T, F>(&'a self, what: &str, op: F) -> Result<T>
+    where
+        F: Fn(&'a dyn Source) -> BoxFuture<'a, Result<T>>,
+    {
+        if let [source] = self.sources.as_slice() {
+            return op(source.as_ref()).await;
+        }
+        let mut errors = vec![];
+        for (i, source) in self.sources.iter().enumerate() {
+            match op(source.as_ref()).await {
+                Ok(v) if i > 0 => {
+                    println!("failed over to mirror {} to {}", source.url(), what);
+                    return Ok(v);
+                }
+                Ok(v) => {
+                    info!("{} served by mirror {}", what, source.url());
+                    return Ok(v);
+                }
+                Err(e) => {
+                    warn!("failed to {} from mirror {}: {}", what, source.url(), e);
+                    errors.push(format!("{}: {}", source.url(), e));
+                }
+            }
+        }
+        Err(anyhow!(
+            "failed to {} from all mirrors:\n  {}",
+            what,
+            errors.join("\n  ")
+        ))
+    }
+}
+
+#[async_trait]
+impl Source for Mirrors {
+    /// 第一个镜像的地址
+    fn url(&self) -> &Url {
+        self.sources[0].url()
+    }
+
+    async fn fetch_versions(&self) -> Result<Vec<Version>> {
+        self.try_each("fetch versions", |source| source.fetch_versions())
+            .await
+    }
+
+    async fn fetch_bins(&self, ver: &Version) -> Result<Vec<BinFile>> {
+        self.try_each(&format!("fetch bins of {}", ver), |source| {
+            source.fetch_bins(ver)
+        })
+        .await
+    }
+
+    /// 使用`bin_url`所在的镜像获取摘要
+    async fn fetch_digest(&self, bin_url: &Url) -> Result<Option<Digest>> {
+        let source = self
+            .sources
+            .iter()
+            .find(|source| bin_url.as_str().starts_with(source.url().as_str()))
+            .unwrap_or(&self.sources[0]);
+        source.fetch_digest(bin_url).await
+    }
+
+    async fn fetch_keys(&self) -> Result<String> {
+        self.try_each("fetch KEYS", |source| source.fetch_keys())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_mirrors_failover() -> Result<()> {
+        let dir = tempdir()?;
+        let (missing, local) = (dir.path().join("missing"), dir.path().join("local"));
+        let bin_dir = local.join("maven/maven-3/3.8.4/binaries");
+        fs::create_dir_all(&bin_dir)?;
+        fs::write(bin_dir.join("apache-maven-3.8.4-bin.zip"), "zip")?;
+        let site = |path: &std::path::Path| -> Result<Box<dyn Source>> {
+            Layout::Dist.source(Mirror::Url(Url::from_directory_path(path).unwrap()))
+        };
+
+        let mirrors = Mirrors::new(vec![site(&missing)?, site(&local)?])?;
+        assert_eq!(mirrors.fetch_versions().await?, vec!["3.8.4".parse()?]);
+        let bins = mirrors.fetch_bins(&"3.8.4".parse()?).await?;
+        assert!(bins[0]
+            .url()
+            .as_str()
+            .starts_with(mirrors.sources()[1].url().as_str()));
+
+        let mirrors = Mirrors::new(vec![site(&missing)?, site(&missing.join("other"))?])?;
+        let e = mirrors.fetch_versions().await.unwrap_err().to_string();
+        assert!(e.contains("from all mirrors"), "{}", e);
+
+        assert!(Mirrors::new(vec![]).is_err());
+        Ok(())
+    }
+}
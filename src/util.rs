@@ -1,37 +1,121 @@
-use std::path::Path;
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Result};
 use cmd_lib::run_fun;
-use log::{debug, error, trace};
+use log::{debug, error, warn};
 use regex::Regex;
-use semver::Version;
 use url::Url;
-use which::which;
 
-use crate::site::BinFile;
+use crate::{
+    archive::ArchiveFormat,
+    java::JavaVersion,
+    site::{BinFile, Digest},
+    version::{Channel, Version, VersionReq},
+};
 
-pub fn match_digests(path: impl AsRef<Path>, bin: &BinFile) -> bool {
-    let data = path.as_ref().metadata().unwrap();
-    // todo: digest
-    data.len() == *bin.size() as u64
+/// 检查本地文件的大小与摘要是否与`bin`一致。大小与摘要都未知时不使用本地文件
+pub fn match_digests(path: impl AsRef<Path>, bin: &BinFile) -> Result<bool> {
+    let path = path.as_ref();
+    match bin.size() {
+        Some(size) if path.metadata()?.len() != *size as u64 => {
+            debug!("{} size is inconsistent with {}", path.display(), size);
+            return Ok(false);
+        }
+        None if bin.digest().is_none() => {
+            debug!("not found size and digest to check {}", path.display());
+            return Ok(false);
+        }
+        _ => {}
+    }
+    if let Some(digest) = bin.digest() {
+        let actual = file_digest(path, digest)?;
+        if let Err(e) = digest.verify(&actual) {
+            warn!("{}: {}", path.display(), e);
+            return Ok(false);
+        }
+    } else {
+        warn!("{} digests not checked", path.display());
+    }
+    Ok(true)
+}
+
+/// 使用`digest`的算法计算文件摘要
+pub fn file_digest(path: impl AsRef<Path>, digest: &Digest) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = digest.hasher();
+    let mut buf = [0; 8192];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
 }
 
-pub fn extract<P: AsRef<Path>>(from: P, to: P) -> Result<()> {
-    let (from, to) = (from.as_ref(), to.as_ref());
-    if !from.is_file() {
-        bail!("{} is not a file", from.display());
+/// 从`vers`中找出与`req`匹配且属于渠道`channel`的版本。
+/// 确定的版本如`=4.0.0-rc-1`不受渠道限制
+pub fn match_versions<'a>(
+    req: &VersionReq,
+    vers: &'a [Version],
+    channel: Channel,
+) -> Vec<&'a Version> {
+    vers.iter()
+        .filter(|v| req.matches(v) && (req.is_exact() || channel.allows(v)))
+        .collect()
+}
+
+/// `file://`地址对应的本地路径
+pub fn local_path(url: &Url) -> Option<PathBuf> {
+    if url.scheme() == "file" {
+        url.to_file_path().ok()
+    } else {
+        None
     }
-    if let Ok(p) = which("tar") {
-        debug!("try using tar to extract {}", from.display());
-        let to_str = to.to_str().unwrap();
-        let out = run_fun!($p xvf $from --directory=$to_str).map_err(|e| {
-            error!("failed to extract by tar: {}", e);
-            e
-        })?;
-        trace!("tar output: {}", out);
-        return Ok(());
+}
+
+/// 解析地址或本地路径。本地路径转换为`file://`地址
+pub fn parse_location(s: &str) -> Result<Url> {
+    match Url::parse(s) {
+        // 忽略windows的盘符如`C:`
+        Ok(url) if url.scheme().len() > 1 => Ok(url),
+        _ => {
+            let path = Path::new(s)
+                .canonicalize()
+                .map_err(|e| anyhow!("invalid location {}: {}", s, e))?;
+            let url = if path.is_dir() {
+                Url::from_directory_path(&path)
+            } else {
+                Url::from_file_path(&path)
+            };
+            url.map_err(|_| anyhow!("invalid path: {}", path.display()))
+        }
     }
-    bail!("failed to extract file {}: unsupported", from.display())
+}
+
+/// 解析镜像地址。目录地址总是以`/`结尾
+pub fn parse_mirror(s: &str) -> Result<Url> {
+    let mut url = parse_location(s)?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+/// 从文件名`apache-maven-<version>-bin.<ext>`中解析版本
+pub fn parse_archive_version(filename: &str) -> Option<Version> {
+    let format = ArchiveFormat::from_filename(filename)?;
+    filename
+        .strip_suffix(format.suffix())?
+        .strip_suffix("-bin")?
+        .strip_prefix("apache-maven-")?
+        .parse()
+        .ok()
 }
 
 /// 从url中查找文件名
@@ -48,10 +132,10 @@ pub fn find_mvn_version(path: impl AsRef<Path>) -> Result<Version> {
     // trace!("running command: {}", cmd);
     let path_str = path.as_ref().to_str().expect("to str error");
     let out = run_fun! {$path_str --version}?;
-    parse_mvn_version(&out)?.parse().map_err(Into::into)
+    parse_mvn_version(&out)?.parse()
 }
 
-pub fn find_java_version(path: impl AsRef<Path>) -> Result<String> {
+pub fn find_java_version(path: impl AsRef<Path>) -> Result<JavaVersion> {
     let path_str = path.as_ref().to_str().expect("to str error");
     // java -version output to stderr
     let out = run_fun! {2>&1 $path_str -version}?;
@@ -59,31 +143,100 @@ pub fn find_java_version(path: impl AsRef<Path>) -> Result<String> {
 }
 
 fn parse_mvn_version(s: &str) -> Result<String> {
-    let re = Regex::new(r"Apache Maven\s*((\d+\.?)*)")?;
+    let re = Regex::new(r"Apache Maven\s*(\d[^\s(]*)")?;
     re.captures(s)
         .and_then(|caps| caps.get(1))
         .map(|m| m.as_str().to_string())
         .ok_or_else(|| {
-            error!("failed to parse java version by regex `{}`: {}", re, s);
+            error!("failed to parse mvn version by regex `{}`: {}", re, s);
             anyhow!("failed to parse mvn version")
         })
 }
 
-fn parse_java_version(s: &str) -> Result<String> {
-    let re = Regex::new(r#"version "(.+)""#)?;
-    re.captures(s)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_string())
-        .ok_or_else(|| {
-            error!("failed to parse java version by regex `{}`: {}", re, s);
-            anyhow!("not found java version")
-        })
+fn parse_java_version(s: &str) -> Result<JavaVersion> {
+    s.parse::<JavaVersion>().map_err(|e| {
+        error!("failed to parse java version: {}", e);
+        anyhow!("not found java version")
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_file_digest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "hello")?;
+
+        let sha1 = Digest::Sha1("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d".to_string());
+        assert_eq!(file_digest(&path, &sha1)?, sha1.value());
+        assert!(sha1.verify(&file_digest(&path, &sha1)?).is_ok());
+
+        let md5 = Digest::Md5("00000000000000000000000000000000".to_string());
+        assert_eq!(
+            file_digest(&path, &md5)?,
+            "5d41402abc4b2a76b9719d911017c592"
+        );
+        assert!(md5.verify(&file_digest(&path, &md5)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_mirror() -> Result<()> {
+        assert_eq!(
+            parse_mirror("https://archive.apache.org/dist")?.as_str(),
+            "https://archive.apache.org/dist/"
+        );
+        let dir = tempfile::tempdir()?;
+        let url = parse_mirror(dir.path().to_str().unwrap())?;
+        assert_eq!(url.scheme(), "file");
+        assert!(url.path().ends_with('/'));
+        assert_eq!(local_path(&url), Some(dir.path().canonicalize()?));
+        assert!(parse_mirror("/not/exists/mirror").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_archive_version() -> Result<()> {
+        assert_eq!(
+            parse_archive_version("apache-maven-3.8.4-bin.tar.gz"),
+            Some("3.8.4".parse()?)
+        );
+        assert_eq!(
+            parse_archive_version("apache-maven-4.0.0-rc-2-bin.zip"),
+            Some("4.0.0-rc-2".parse()?)
+        );
+        assert_eq!(parse_archive_version("apache-maven-3.8.4-src.tar.gz"), None);
+        assert_eq!(parse_archive_version("maven.zip"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_versions() -> Result<()> {
+        let vers = ["4.0.0-rc-2", "4.0.0-rc-1", "3.9.6", "3.8.4"]
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<Vec<Version>>>()?;
+        let matched = |req: &str, channel: Channel| -> Result<Vec<String>> {
+            Ok(match_versions(&req.parse()?, &vers, channel)
+                .into_iter()
+                .map(ToString::to_string)
+                .collect())
+        };
+        assert!(matched("4", Channel::Stable)?.is_empty());
+        assert_eq!(matched("4", Channel::Rc)?, vec!["4.0.0-rc-2", "4.0.0-rc-1"]);
+        assert_eq!(matched("=4.0.0-rc-1", Channel::Stable)?, vec!["4.0.0-rc-1"]);
+        assert_eq!(matched(">=3.9", Channel::Stable)?, vec!["3.9.6"]);
+        assert_eq!(
+            matched(">=3.9", Channel::Alpha)?,
+            vec!["4.0.0-rc-2", "4.0.0-rc-1", "3.9.6"]
+        );
+        assert!(matched("5", Channel::Alpha)?.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_parse_mvn_version() -> Result<()> {
         let out = r#"Apache Maven 3.8.3 (ff8e977a158738155dc465c6a97ffaf31982d739)
@@ -92,6 +245,9 @@ Java version: 17, vendor: Private Build, runtime: /usr/lib/jvm/java-17-openjdk-a
 Default locale: en, platform encoding: UTF-8
 OS name: "linux", version: "5.10.60.1-microsoft-standard-wsl2", arch: "amd64", family: "unix""#;
         assert_eq!(parse_mvn_version(out)?, "3.8.3".to_string());
+
+        let out = "\u{1b}[1mApache Maven 4.0.0-rc-2 (fb1a2b7c8d)\u{1b}[m\nMaven home: /opt/maven";
+        assert_eq!(parse_mvn_version(out)?, "4.0.0-rc-2".to_string());
         Ok(())
     }
 
@@ -100,12 +256,15 @@ OS name: "linux", version: "5.10.60.1-microsoft-standard-wsl2", arch: "amd64", f
         let ver_17 = r#"openjdk version "17" 2021-09-14
 OpenJDK Runtime Environment (build 17+35-Ubuntu-120.04)
 OpenJDK 64-Bit Server VM (build 17+35-Ubuntu-120.04, mixed mode, sharing)"#;
-        assert_eq!(parse_java_version(ver_17)?, "17");
+        assert_eq!(*parse_java_version(ver_17)?.feature(), 17);
 
         let ver_8 = r#"openjdk version "1.8.0_312"
 OpenJDK Runtime Environment (build 1.8.0_312-b07)
 OpenJDK 64-Bit Server VM (build 25.312-b07, mixed mode)"#;
-        assert_eq!(parse_java_version(ver_8)?, "1.8.0_312");
+        let ver = parse_java_version(ver_8)?;
+        assert_eq!(*ver.feature(), 8);
+        assert_eq!(ver.raw(), "1.8.0_312");
+        assert!(ver < parse_java_version(ver_17)?);
 
         // localhost
         // let ver = find_java_version(which::which("java")?)?;
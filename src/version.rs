@@ -0,0 +1,591 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use strum::{AsRefStr, EnumString, EnumVariantNames};
+
+/// maven已知的限定符，按从小到大排序。空字符串表示正式版本
+static QUALIFIERS: [&str; 7] = ["alpha", "beta", "milestone", "rc", "snapshot", "", "sp"];
+
+/// 正式版本在`QUALIFIERS`中的位置
+const RELEASE_INDEX: usize = 5;
+
+#[derive(Debug, Clone)]
+enum Item {
+    Int(u64),
+    Str(String),
+    List(Vec<Item>),
+}
+
+impl Item {
+    fn parse(is_digit: bool, s: &str) -> Result<Self> {
+        if is_digit {
+            s.parse()
+                .map(Item::Int)
+                .map_err(|e| anyhow!("invalid number {} in version: {}", s, e))
+        } else {
+            Ok(Item::Str(qualifier(s, false)))
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        match self {
+            Item::Int(n) => *n == 0,
+            Item::Str(s) => s.is_empty(),
+            Item::List(items) => items.is_empty(),
+        }
+    }
+
+    /// 与maven中`ComparableVersion.Item#compareTo`一致，`None`表示缺少的项
+    fn compare(&self, other: Option<&Item>) -> Ordering {
+        match (self, other) {
+            (Item::Int(n), None) => n.cmp(&0),
+            (Item::Int(a), Some(Item::Int(b))) => a.cmp(b),
+            (Item::Int(_), Some(_)) => Ordering::Greater,
+
+            (Item::Str(s), None) => comparable_qualifier(s).cmp(&RELEASE_INDEX.to_string()),
+            (Item::Str(a), Some(Item::Str(b))) => {
+                comparable_qualifier(a).cmp(&comparable_qualifier(b))
+            }
+            (Item::Str(_), Some(_)) => Ordering::Less,
+
+            (Item::List(items), None) => items
+                .first()
+                .map(|first| first.compare(None))
+                .unwrap_or(Ordering::Equal),
+            (Item::List(_), Some(Item::Int(_))) => Ordering::Less,
+            (Item::List(_), Some(Item::Str(_))) => Ordering::Greater,
+            (Item::List(a), Some(Item::List(b))) => compare_items(a, b),
+        }
+    }
+}
+
+fn compare_items(a: &[Item], b: &[Item]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ord = match (a.get(i), b.get(i)) {
+            (None, None) => Ordering::Equal,
+            (None, Some(r)) => r.compare(None).reverse(),
+            (Some(l), r) => l.compare(r),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+/// 规范化限定符。如：紧跟数字的`a1`为`alpha1`，`ga`、`final`为正式版本
+fn qualifier(s: &str, followed_by_digit: bool) -> String {
+    let s = match s {
+        "a" if followed_by_digit => "alpha",
+        "b" if followed_by_digit => "beta",
+        "m" if followed_by_digit => "milestone",
+        "ga" | "final" | "release" => "",
+        "cr" => "rc",
+        s => s,
+    };
+    s.to_string()
+}
+
+/// 未知的限定符排在所有已知限定符之后，按字符串比较
+fn comparable_qualifier(s: &str) -> String {
+    QUALIFIERS
+        .iter()
+        .position(|q| *q == s)
+        .map(|i| i.to_string())
+        .unwrap_or_else(|| format!("{}-{}", QUALIFIERS.len(), s))
+}
+
+/// 移除末尾的空项，如`1.0.0`与`1`相同
+fn normalize(items: &mut Vec<Item>) {
+    for i in (0..items.len()).rev() {
+        if items[i].is_null() {
+            items.remove(i);
+        } else if !matches!(items[i], Item::List(_)) {
+            break;
+        }
+    }
+}
+
+/// maven版本，与maven中的`ComparableVersion`有相同的顺序。如：
+/// `4.0.0-alpha-2 < 4.0.0-alpha-13 < 4.0.0-beta-1 < 4.0.0-rc-1 < 4.0.0 < 4.0.0-sp-1`
+#[derive(Debug, Clone)]
+pub struct Version {
+    raw: String,
+    items: Vec<Item>,
+}
+
+impl Version {
+    /// 开头的数字部分。如：`4.0.0-rc-1`为`[4]`，末尾的0被移除
+    pub fn numbers(&self) -> Vec<u64> {
+        self.items
+            .iter()
+            .map_while(|item| match item {
+                Item::Int(n) => Some(*n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn major(&self) -> u64 {
+        self.numbers().first().copied().unwrap_or_default()
+    }
+
+    /// 是否是alpha、beta、milestone、rc或snapshot版本
+    pub fn is_prerelease(&self) -> bool {
+        fn has_prerelease(items: &[Item]) -> bool {
+            items.iter().any(|item| match item {
+                Item::Str(s) => QUALIFIERS[..RELEASE_INDEX].contains(&s.as_str()),
+                Item::List(items) => has_prerelease(items),
+                Item::Int(_) => false,
+            })
+        }
+        has_prerelease(&self.items)
+    }
+
+    /// 版本所属的发布渠道。如：`4.0.0-rc-1`属于rc，`4.0.0-alpha-1-rc`属于alpha
+    pub fn channel(&self) -> Channel {
+        fn min_channel(items: &[Item]) -> Channel {
+            items
+                .iter()
+                .map(|item| match item {
+                    Item::Str(s) => match s.as_str() {
+                        "alpha" | "snapshot" => Channel::Alpha,
+                        "beta" => Channel::Beta,
+                        "milestone" => Channel::Milestone,
+                        "rc" => Channel::Rc,
+                        _ => Channel::Stable,
+                    },
+                    Item::List(items) => min_channel(items),
+                    Item::Int(_) => Channel::Stable,
+                })
+                .min()
+                .unwrap_or(Channel::Stable)
+        }
+        min_channel(&self.items)
+    }
+
+    /// 开头的数字部分是否为`prefix`。如：`3.8.4`的前缀可以是`3`、`3.8`、`3.8.4`
+    fn has_prefix(&self, prefix: &[u64]) -> bool {
+        let nums = self.numbers();
+        prefix
+            .iter()
+            .enumerate()
+            .all(|(i, n)| nums.get(i).copied().unwrap_or_default() == *n)
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        if !raw.starts_with(|c: char| c.is_ascii_digit()) {
+            bail!("invalid version: {}", s);
+        }
+        let s = raw.to_ascii_lowercase();
+        let mut stack = vec![vec![]];
+        let mut is_digit = false;
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            let list = stack.last_mut().expect("empty stack");
+            match c {
+                '.' | '-' => {
+                    list.push(if i == start {
+                        Item::Int(0)
+                    } else {
+                        Item::parse(is_digit, &s[start..i])?
+                    });
+                    start = i + 1;
+                    if c == '-' {
+                        stack.push(vec![]);
+                    }
+                }
+                c if c.is_ascii_digit() => {
+                    // 如：`alpha1`
+                    if !is_digit && i > start {
+                        list.push(Item::Str(qualifier(&s[start..i], true)));
+                        start = i;
+                        stack.push(vec![]);
+                    }
+                    is_digit = true;
+                }
+                _ => {
+                    // 如：`1alpha`
+                    if is_digit && i > start {
+                        list.push(Item::parse(true, &s[start..i])?);
+                        start = i;
+                        stack.push(vec![]);
+                    }
+                    is_digit = false;
+                }
+            }
+        }
+        if s.len() > start {
+            let item = Item::parse(is_digit, &s[start..])?;
+            stack.last_mut().expect("empty stack").push(item);
+        }
+
+        let mut items = stack.pop().expect("empty stack");
+        normalize(&mut items);
+        while let Some(mut parent) = stack.pop() {
+            parent.push(Item::List(items));
+            normalize(&mut parent);
+            items = parent;
+        }
+        Ok(Self {
+            raw: raw.to_string(),
+            items,
+        })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_items(&self.items, &other.items)
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Comparator {
+    Eq(Version),
+    Gt(Version),
+    Ge(Version),
+    Lt(Version),
+    Le(Version),
+    /// 开头的数字部分相同
+    Prefix(Vec<u64>),
+}
+
+impl Comparator {
+    fn matches(&self, ver: &Version) -> bool {
+        match self {
+            Comparator::Eq(v) => ver == v,
+            Comparator::Gt(v) => ver > v,
+            Comparator::Ge(v) => ver >= v,
+            Comparator::Lt(v) => ver < v,
+            Comparator::Le(v) => ver <= v,
+            Comparator::Prefix(prefix) => ver.has_prefix(prefix),
+        }
+    }
+
+    /// 解析`>=3.6`、`~3.6`、`3.8`等表达式
+    fn parse(s: &str) -> Result<Vec<Self>> {
+        let s = s.trim();
+        let op_len = s
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| anyhow!("invalid version requirement: {}", s))?;
+        let ver = s[op_len..].parse::<Version>()?;
+        // 书写的数字个数，如`3.0`为2
+        let len = s[op_len..]
+            .split(['.', '-'])
+            .take_while(|n| n.parse::<u64>().is_ok())
+            .count();
+        let prefix = |len: usize| -> Vec<u64> {
+            ver.numbers()
+                .into_iter()
+                .chain(std::iter::repeat(0))
+                .take(len)
+                .collect()
+        };
+        let comparators = match s[..op_len].trim() {
+            "=" => vec![Comparator::Eq(ver)],
+            ">" => vec![Comparator::Gt(ver)],
+            ">=" => vec![Comparator::Ge(ver)],
+            "<" => vec![Comparator::Lt(ver)],
+            "<=" => vec![Comparator::Le(ver)],
+            // ~3.6.1匹配3.6.x，~3匹配3.x
+            "~" => vec![
+                Comparator::Prefix(prefix(len.clamp(1, 2))),
+                Comparator::Ge(ver),
+            ],
+            "^" => vec![Comparator::Prefix(prefix(1)), Comparator::Ge(ver)],
+            // 3.8匹配3.8.x，完整的版本如3.8.4只匹配3.8.4
+            "" if ver.items.len() == ver.numbers().len() && len < 3 => {
+                vec![Comparator::Prefix(prefix(len))]
+            }
+            "" => vec![Comparator::Eq(ver)],
+            op => bail!("invalid operator {} in {}", op, s),
+        };
+        Ok(comparators)
+    }
+
+    /// 解析maven的版本范围。如：`[3.6,3.9)`、`(,3.8.4]`、`[3.8.4]`
+    fn parse_range(s: &str) -> Result<Vec<Self>> {
+        let invalid = || anyhow!("invalid version range: {}", s);
+        let inclusive_start = s.starts_with('[');
+        let inclusive_end = s.ends_with(']');
+        if !(inclusive_start || s.starts_with('(')) || !(inclusive_end || s.ends_with(')')) {
+            return Err(invalid());
+        }
+        let inner = &s[1..s.len() - 1];
+        let parse = |s: &str| -> Result<Option<Version>> {
+            let s = s.trim();
+            (!s.is_empty()).then(|| s.parse()).transpose()
+        };
+        match inner.split_once(',') {
+            None if inclusive_start && inclusive_end => {
+                Ok(vec![Comparator::Eq(parse(inner)?.ok_or_else(invalid)?)])
+            }
+            None => Err(invalid()),
+            Some((start, end)) => {
+                let mut comparators = vec![];
+                if let Some(ver) = parse(start)? {
+                    comparators.push(if inclusive_start {
+                        Comparator::Ge(ver)
+                    } else {
+                        Comparator::Gt(ver)
+                    });
+                }
+                if let Some(ver) = parse(end)? {
+                    comparators.push(if inclusive_end {
+                        Comparator::Le(ver)
+                    } else {
+                        Comparator::Lt(ver)
+                    });
+                }
+                Ok(comparators)
+            }
+        }
+    }
+}
+
+/// 版本要求。支持maven的版本范围如`[3.6,3.9),[4.0,)`，
+/// 与`=3.8.4`、`>=3.6, <3.9`、`~3.6`、`^3`等表达式。
+/// 只有数字的版本如`3.8`表示前缀匹配，可匹配`3.8.4`
+#[derive(Debug, Clone)]
+pub struct VersionReq {
+    raw: String,
+    /// 满足任意一组中所有比较即匹配
+    ranges: Vec<Vec<Comparator>>,
+}
+
+impl VersionReq {
+    /// 是否只匹配一个确定的版本。如：`=4.0.0-rc-1`、`[3.8.4]`
+    pub fn is_exact(&self) -> bool {
+        matches!(self.ranges.as_slice(), [range] if matches!(range.as_slice(), [Comparator::Eq(_)]))
+    }
+
+    pub fn matches(&self, ver: &Version) -> bool {
+        self.ranges
+            .iter()
+            .any(|comparators| comparators.iter().all(|c| c.matches(ver)))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        let ranges = if raw == "*" {
+            vec![vec![]]
+        } else if raw.starts_with(['[', '(']) {
+            let mut ranges = vec![];
+            let mut rest = raw;
+            while !rest.is_empty() {
+                let end = rest
+                    .find([']', ')'])
+                    .ok_or_else(|| anyhow!("invalid version range: {}", raw))?;
+                ranges.push(Comparator::parse_range(&rest[..=end])?);
+                rest = rest[end + 1..].trim_start_matches([',', ' ']);
+            }
+            ranges
+        } else {
+            let comparators = raw
+                .split(',')
+                .map(Comparator::parse)
+                .collect::<Result<Vec<_>>>()?;
+            vec![comparators.into_iter().flatten().collect()]
+        };
+        Ok(Self {
+            raw: raw.to_string(),
+            ranges,
+        })
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// 发布渠道。按稳定程度从低到高排序，渠道包含所有比它稳定的版本，
+/// 如rc渠道包含rc与正式版本
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumString,
+    EnumVariantNames,
+    AsRefStr,
+    Deserialize,
+    Default,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Alpha,
+    Beta,
+    Milestone,
+    Rc,
+    #[default]
+    Stable,
+}
+
+impl Channel {
+    pub fn allows(&self, ver: &Version) -> bool {
+        ver.channel() >= *self
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ordering() {
+        let vers = [
+            "3.0-alpha-1",
+            "3.0-beta-1",
+            "3.0",
+            "3.5.0-alpha-1",
+            "3.5.0-beta-1",
+            "3.5.0",
+            "3.8.4",
+            "4.0.0-alpha-2",
+            "4.0.0-alpha-13",
+            "4.0.0-beta-3",
+            "4.0.0-rc-1",
+            "4.0.0-rc-2",
+            "4.0.0",
+            "4.0.0-sp-1",
+            "4.0.1-SNAPSHOT",
+            "4.0.1",
+        ];
+        for w in vers.windows(2) {
+            assert!(v(w[0]) < v(w[1]), "{} < {}", w[0], w[1]);
+        }
+        assert_eq!(v("3.8"), v("3.8.0"));
+        assert_eq!(v("4.0.0-RC1"), v("4.0.0-cr-1"));
+        assert_eq!(v("1.0a1"), v("1.0-alpha-1"));
+        assert_eq!(v("1.0.0-ga"), v("1"));
+        assert_eq!(v("4.0.0-rc-1").to_string(), "4.0.0-rc-1");
+        assert!("binaries".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_version_info() {
+        assert_eq!(v("4.0.0-rc-1").numbers(), vec![4]);
+        assert_eq!(v("3.8.4").numbers(), vec![3, 8, 4]);
+        assert_eq!(v("3.8.4").major(), 3);
+        assert!(v("4.0.0-rc-1").is_prerelease());
+        assert!(v("4.0.1-SNAPSHOT").is_prerelease());
+        assert!(!v("3.8.4").is_prerelease());
+        assert!(!v("4.0.0-sp-1").is_prerelease());
+
+        assert_eq!(v("4.0.0-rc-1").channel(), Channel::Rc);
+        assert_eq!(v("4.0.0-M1").channel(), Channel::Milestone);
+        assert_eq!(v("3.8.4").channel(), Channel::Stable);
+        assert!(Channel::Rc.allows(&v("3.8.4")));
+        assert!(Channel::Rc.allows(&v("4.0.0-rc-1")));
+        assert!(!Channel::Rc.allows(&v("4.0.0-beta-3")));
+        assert!(!Channel::default().allows(&v("4.0.0-rc-1")));
+        assert_eq!("rc".parse::<Channel>().unwrap(), Channel::Rc);
+    }
+
+    #[test]
+    fn test_matches() -> Result<()> {
+        let matches = |req: &str, ver: &str| -> Result<bool> {
+            Ok(req.parse::<VersionReq>()?.matches(&v(ver)))
+        };
+        assert!(matches("3.8", "3.8.4")?);
+        assert!(matches("3.8.0", "3.8")?);
+        assert!(!matches("3.8", "3.9.0")?);
+        assert!(matches("4", "4.0.0-rc-1")?);
+        assert!(matches("3.8.4", "3.8.4")?);
+        assert!(!matches("3.8.4", "3.8.4.1")?);
+        assert!(!matches("3.8.4", "3.8.4-rc-1")?);
+        assert!(!matches("3.8.4", "3.8.5")?);
+        assert!(matches("=3.8.4", "3.8.4")?);
+        assert!(!matches("=3.8.4", "3.8.5")?);
+        assert!(matches("4.0.0-rc-1", "4.0.0-rc-1")?);
+        assert!(!matches("4.0.0-rc-1", "4.0.0-rc-2")?);
+        assert!(matches(">=4.0.0-rc-1", "4.0.0-rc-2")?);
+        assert!(!matches(">=4.0.0-rc-1", "4.0.0-beta-3")?);
+        assert!(matches(">=3.6, <3.9", "3.8.4")?);
+        assert!(!matches(">=3.6, <3.9", "3.9.0")?);
+        assert!(matches("~3.6", "3.6.3")?);
+        assert!(!matches("~3.6.3", "3.6.2")?);
+        assert!(!matches("~3.6", "3.8.4")?);
+        assert!(!matches("~3.0", "3.1.0")?);
+        assert!(matches("^3.6", "3.9.6")?);
+        assert!(!matches("^3.6", "4.0.0")?);
+        assert!(matches("*", "3.8.4")?);
+
+        assert!(matches("[3.6,3.9)", "3.8.4")?);
+        assert!(!matches("[3.6,3.9)", "3.9.0")?);
+        assert!(matches("(,3.8.4]", "3.8.4")?);
+        assert!(!matches("(3.8.4,)", "3.8.4")?);
+        assert!(matches("[3.8.4]", "3.8.4")?);
+        assert!(matches("[3.0,3.6),[3.8,)", "4.0.0")?);
+        assert!(!matches("[3.0,3.6),[3.8,)", "3.6.3")?);
+
+        assert!("=4.0.0-rc-1".parse::<VersionReq>()?.is_exact());
+        assert!("[3.8.4]".parse::<VersionReq>()?.is_exact());
+        assert!(!"3.8".parse::<VersionReq>()?.is_exact());
+
+        assert!("abc".parse::<VersionReq>().is_err());
+        assert!("[3.6,3.9".parse::<VersionReq>().is_err());
+        Ok(())
+    }
+}